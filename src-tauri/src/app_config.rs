@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Machine-level settings that must be known before the database can be opened,
+/// stored as `settings.json` in the app config dir.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppConfig {
+    #[serde(rename = "dataDir", default)]
+    pub data_dir: Option<PathBuf>,
+    #[serde(rename = "previousDataDirs", default)]
+    pub previous_data_dirs: Vec<PathBuf>,
+    /// Folder of the last file saved through `file_save`
+    #[serde(rename = "lastSaveDir", default)]
+    pub last_save_dir: Option<PathBuf>,
+}
+
+impl AppConfig {
+    pub fn settings_path(config_dir: &Path) -> PathBuf {
+        config_dir.join(SETTINGS_FILE_NAME)
+    }
+
+    pub fn load(config_dir: &Path) -> Result<Self, AppError> {
+        let path = Self::settings_path(config_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| AppError::invalid_input(format!("设置文件格式错误 ({}): {}", path.display(), e)))
+    }
+
+    /// Writes through a temporary file so a crash never leaves a half-written settings file.
+    pub fn save(&self, config_dir: &Path) -> Result<(), AppError> {
+        std::fs::create_dir_all(config_dir)?;
+
+        let path = Self::settings_path(config_dir);
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+
+    pub fn remember_previous_data_dir(&mut self, dir: &Path) {
+        if !self.previous_data_dirs.iter().any(|previous| previous == dir) {
+            self.previous_data_dirs.push(dir.to_path_buf());
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use crate::error::AppError;
+use crate::migrations;
+use crate::models::BackupInfo;
+
+// Backup kinds, embedded in the file name as pointhub-{kind}-{timestamp}.db
+pub const BACKUP_KIND_MANUAL: &str = "manual";
+pub const BACKUP_KIND_STARTUP: &str = "startup";
+pub const BACKUP_KIND_PRE_PURGE: &str = "pre-purge";
+pub const BACKUP_KIND_PRE_ROLLOVER: &str = "pre-rollover";
+pub const BACKUP_KIND_PRE_REPAIR: &str = "pre-repair";
+pub const BACKUP_KIND_PRE_RESTORE: &str = "pre-restore";
+// Migration backups are named v{N}-pre-migration after the schema version they preserve
+pub const BACKUP_KIND_PRE_MIGRATION_SUFFIX: &str = "pre-migration";
+
+const BACKUP_FILE_PREFIX: &str = "pointhub-";
+const BACKUP_FILE_EXTENSION: &str = "db";
+
+/// Backups live next to the database so they move together with it.
+pub fn backup_dir(db_path: &Path) -> Result<PathBuf, AppError> {
+    let dir = db_path
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .ok_or_else(|| AppError::io("无法确定数据库备份目录"))?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Copies the live database page by page with SQLite's online backup API.
+pub fn create_backup(conn: &Connection, db_path: &Path, kind: &str) -> Result<BackupInfo, AppError> {
+    let file_name = format!(
+        "{}{}-{}.{}",
+        BACKUP_FILE_PREFIX,
+        kind,
+        Utc::now().format("%Y%m%d-%H%M%S%3f"),
+        BACKUP_FILE_EXTENSION
+    );
+    let backup_path = backup_dir(db_path)?.join(&file_name);
+
+    conn.backup(DatabaseName::Main, &backup_path, None)
+        .map_err(|e| AppError::io(format!("创建备份失败: {}", e)))?;
+
+    backup_info(&backup_path)
+}
+
+pub fn list_backups(db_path: &Path) -> Result<Vec<BackupInfo>, AppError> {
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(backup_dir(db_path)?)? {
+        let path = entry?.path();
+        let is_backup = path.extension().is_some_and(|ext| ext == BACKUP_FILE_EXTENSION)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BACKUP_FILE_PREFIX));
+        if is_backup {
+            backups.push(backup_info(&path)?);
+        }
+    }
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Keeps the `keep` newest automatic backups. Manual and pre-migration backups are never
+/// rotated out, the latter being the only copy of the schema before an upgrade.
+pub fn prune_automatic_backups(db_path: &Path, keep: usize) -> Result<(), AppError> {
+    let dir = backup_dir(db_path)?;
+    let expired = list_backups(db_path)?
+        .into_iter()
+        .filter(|backup| {
+            backup.kind != BACKUP_KIND_MANUAL && !backup.kind.ends_with(BACKUP_KIND_PRE_MIGRATION_SUFFIX)
+        })
+        .skip(keep);
+
+    for backup in expired {
+        std::fs::remove_file(dir.join(&backup.file_name))?;
+    }
+    Ok(())
+}
+
+/// Resolves a file name from `list_backups`, refusing anything outside the backup directory.
+pub fn resolve_backup_path(db_path: &Path, file_name: &str) -> Result<PathBuf, AppError> {
+    if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.contains("..") {
+        return Err(AppError::invalid_input("无效的备份文件名"));
+    }
+
+    let path = backup_dir(db_path)?.join(file_name);
+    if !path.is_file() {
+        return Err(AppError::not_found("备份文件不存在"));
+    }
+    Ok(path)
+}
+
+/// Checks that the file is an intact PointHub database this build can open, and returns
+/// its schema version.
+pub fn validate_backup(path: &Path) -> Result<i64, AppError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::invalid_input(format!("无法打开备份文件: {}", e)))?;
+
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| AppError::invalid_input(format!("备份文件已损坏: {}", e)))?;
+    if integrity != "ok" {
+        return Err(AppError::invalid_input(format!("备份文件已损坏: {}", integrity)));
+    }
+
+    let version = migrations::current_version(&conn)?;
+    if version <= 0 {
+        return Err(AppError::invalid_input("备份文件不是有效的 PointHub 数据库"));
+    }
+    if version > migrations::latest_version() {
+        return Err(AppError::invalid_input(format!(
+            "备份文件版本 (v{}) 高于当前程序支持的版本 (v{})，请先升级程序",
+            version,
+            migrations::latest_version()
+        )));
+    }
+
+    Ok(version)
+}
+
+fn backup_info(path: &Path) -> Result<BackupInfo, AppError> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| AppError::invalid_input("无效的备份文件名"))?
+        .to_string();
+
+    // pointhub-{kind}-{date}-{time}.db
+    let kind = file_name
+        .trim_start_matches(BACKUP_FILE_PREFIX)
+        .trim_end_matches(&format!(".{}", BACKUP_FILE_EXTENSION))
+        .rsplitn(3, '-')
+        .nth(2)
+        .unwrap_or_default()
+        .to_string();
+
+    let metadata = std::fs::metadata(path)?;
+    let created_at: DateTime<Utc> = metadata.modified()?.into();
+
+    let schema_version = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .ok()
+        .and_then(|conn| migrations::current_version(&conn).ok());
+
+    Ok(BackupInfo {
+        file_name,
+        kind,
+        size_bytes: metadata.len(),
+        schema_version,
+        created_at,
+    })
+}
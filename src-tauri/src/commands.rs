@@ -1,147 +1,409 @@
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 use crate::database::Database;
-use crate::models::{Class, Student, CreateClassRequest, UpdateClassRequest, CreateStudentRequest, UpdateStudentRequest, Product, CreateProductRequest, UpdateProductRequest, PurchaseRecord, CreatePurchaseRequest, UpdateShippingStatusRequest, PaginatedPurchaseRecords, WheelConfig, SaveWheelConfigRequest, SpinWheelRequest, SpinWheelResult};
-use std::fs;
+use crate::error::AppError;
+use crate::export::ExportFile;
+use crate::file_save;
+use crate::app_config::AppConfig;
+use crate::models::{Class, Student, CreateClassRequest, UpdateClassRequest, CreateStudentRequest, UpdateStudentRequest, Product, CreateProductRequest, UpdateProductRequest, PurchaseRecord, CreatePurchaseRequest, UpdateShippingStatusRequest, PaginatedPurchaseRecords, WheelConfig, SaveWheelConfigRequest, SpinWheelRequest, SpinWheelResult, SchemaInfo, PointTransaction, PaginatedPointTransactions, PointAdjustmentResult, PointSettings, BatchAdjustPointsRequest, BatchAdjustPointsResult, PointReason, CreatePointReasonRequest, UpdatePointReasonRequest, PaginatedAuditLogs, TrashItem, PurgeTrashResult, ClassDeletionPreview, RolloverClassRequest, ArchivedTerm, ArchivedTermDetail, TransferStudentRequest, TransferStudentResult, StudentTransfer, IntegrityReport, RepairIntegrityResult, BackupInfo, BackupSettings, RestoreBackupResult, DataLocation, DetectedDatabase, ClassBundle, ClassBundleImportPreview, ImportClassBundleRequest, ImportClassBundleResult, StudentImportReport, StudentImportPreview, CommitStudentImportRequest, CommitStudentImportResult, ExportPurchaseRecordsRequest, ExportFileResult, SavedFile, RefundPurchaseRequest, RefundPurchaseResult, BulkShippingResult, PickList};
 
 // Class commands
 #[tauri::command]
-pub async fn get_classes(database: State<'_, Database>) -> Result<Vec<Class>, String> {
+pub async fn get_classes(database: State<'_, Database>) -> Result<Vec<Class>, AppError> {
     database.get_all_classes()
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn create_class(database: State<'_, Database>, request: CreateClassRequest) -> Result<Class, String> {
+pub async fn create_class(database: State<'_, Database>, request: CreateClassRequest) -> Result<Class, AppError> {
     database.create_class(request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn update_class(database: State<'_, Database>, id: String, request: UpdateClassRequest) -> Result<Class, String> {
+pub async fn update_class(database: State<'_, Database>, id: String, request: UpdateClassRequest) -> Result<Class, AppError> {
     database.update_class(&id, request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_class(database: State<'_, Database>, id: String) -> Result<(), String> {
+pub async fn delete_class(database: State<'_, Database>, id: String) -> Result<(), AppError> {
     database.delete_class(&id)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_class_deletion(database: State<'_, Database>, id: String) -> Result<ClassDeletionPreview, AppError> {
+    database.preview_class_deletion(&id)
 }
 
 // Student commands
 #[tauri::command]
-pub async fn get_students(database: State<'_, Database>) -> Result<Vec<Student>, String> {
+pub async fn get_students(database: State<'_, Database>) -> Result<Vec<Student>, AppError> {
     database.get_all_students()
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_students_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<Student>, String> {
+pub async fn get_students_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<Student>, AppError> {
     database.get_students_by_class(&class_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn create_student(database: State<'_, Database>, request: CreateStudentRequest) -> Result<Student, String> {
+pub async fn create_student(database: State<'_, Database>, request: CreateStudentRequest) -> Result<Student, AppError> {
     database.create_student(request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn update_student(database: State<'_, Database>, id: String, request: UpdateStudentRequest) -> Result<Student, String> {
+pub async fn update_student(database: State<'_, Database>, id: String, request: UpdateStudentRequest) -> Result<Student, AppError> {
     database.update_student(&id, request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_student(database: State<'_, Database>, id: String) -> Result<(), String> {
+pub async fn transfer_student(database: State<'_, Database>, id: String, request: TransferStudentRequest) -> Result<TransferStudentResult, AppError> {
+    database.transfer_student(&id, request)
+}
+
+#[tauri::command]
+pub async fn get_student_transfers(database: State<'_, Database>, student_id: String) -> Result<Vec<StudentTransfer>, AppError> {
+    database.get_student_transfers(&student_id)
+}
+
+#[tauri::command]
+pub async fn import_students_xlsx(database: State<'_, Database>, class_id: String, bytes: Vec<u8>) -> Result<StudentImportReport, AppError> {
+    database.import_students_xlsx(&class_id, bytes)
+}
+
+#[tauri::command]
+pub async fn preview_student_import(database: State<'_, Database>, class_id: String, bytes: Vec<u8>) -> Result<StudentImportPreview, AppError> {
+    database.preview_student_import(&class_id, bytes)
+}
+
+#[tauri::command]
+pub async fn commit_student_import(database: State<'_, Database>, class_id: String, request: CommitStudentImportRequest) -> Result<CommitStudentImportResult, AppError> {
+    database.commit_student_import(&class_id, request)
+}
+
+#[tauri::command]
+pub async fn delete_student(database: State<'_, Database>, id: String) -> Result<(), AppError> {
     database.delete_student(&id)
-        .map_err(|e| e.to_string())
+}
+
+// Point adjustment commands
+#[tauri::command]
+pub async fn adjust_student_points(database: State<'_, Database>, student_id: String, delta: i32, reason: Option<String>, reason_id: Option<String>) -> Result<PointAdjustmentResult, AppError> {
+    database.adjust_student_points(&student_id, delta, reason.as_deref(), reason_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn batch_adjust_points(database: State<'_, Database>, request: BatchAdjustPointsRequest) -> Result<BatchAdjustPointsResult, AppError> {
+    database.batch_adjust_points(request)
+}
+
+#[tauri::command]
+pub async fn undo_point_operation(database: State<'_, Database>, transaction_id: String) -> Result<Vec<PointTransaction>, AppError> {
+    database.undo_point_operation(&transaction_id)
+}
+
+#[tauri::command]
+pub async fn undo_last_point_operations(database: State<'_, Database>, class_id: String, count: i64) -> Result<Vec<PointTransaction>, AppError> {
+    database.undo_last_point_operations(&class_id, count)
+}
+
+#[tauri::command]
+pub async fn get_point_settings(database: State<'_, Database>) -> Result<PointSettings, AppError> {
+    database.get_point_settings()
+}
+
+#[tauri::command]
+pub async fn update_point_settings(database: State<'_, Database>, request: PointSettings) -> Result<PointSettings, AppError> {
+    database.update_point_settings(request)
+}
+
+// Point reason commands
+#[tauri::command]
+pub async fn get_point_reasons_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<PointReason>, AppError> {
+    database.get_point_reasons_by_class(&class_id)
+}
+
+#[tauri::command]
+pub async fn create_point_reason(database: State<'_, Database>, request: CreatePointReasonRequest) -> Result<PointReason, AppError> {
+    database.create_point_reason(request)
+}
+
+#[tauri::command]
+pub async fn update_point_reason(database: State<'_, Database>, id: String, request: UpdatePointReasonRequest) -> Result<PointReason, AppError> {
+    database.update_point_reason(&id, request)
+}
+
+#[tauri::command]
+pub async fn delete_point_reason(database: State<'_, Database>, id: String) -> Result<(), AppError> {
+    database.delete_point_reason(&id)
+}
+
+// Point ledger commands
+#[tauri::command]
+pub async fn get_student_point_transactions(database: State<'_, Database>, student_id: String) -> Result<Vec<PointTransaction>, AppError> {
+    database.get_student_point_transactions(&student_id)
+}
+
+#[tauri::command]
+pub async fn get_point_transactions_paginated(database: State<'_, Database>, class_id: String, page: i64, page_size: i64, student_id: Option<String>) -> Result<PaginatedPointTransactions, AppError> {
+    database.get_point_transactions_paginated(&class_id, page, page_size, student_id.as_deref())
 }
 
 // Product commands
 #[tauri::command]
-pub async fn get_products_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<Product>, String> {
+pub async fn get_products_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<Product>, AppError> {
     database.get_products_by_class(&class_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn create_product(database: State<'_, Database>, request: CreateProductRequest) -> Result<Product, String> {
+pub async fn create_product(database: State<'_, Database>, request: CreateProductRequest) -> Result<Product, AppError> {
     database.create_product(request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn update_product(database: State<'_, Database>, id: String, request: UpdateProductRequest) -> Result<Product, String> {
+pub async fn update_product(database: State<'_, Database>, id: String, request: UpdateProductRequest) -> Result<Product, AppError> {
     database.update_product(&id, request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_product(database: State<'_, Database>, id: String) -> Result<(), String> {
-    database.delete_product(&id)
-        .map_err(|e| e.to_string())
+pub async fn delete_product(database: State<'_, Database>, id: String, force: Option<bool>) -> Result<(), AppError> {
+    database.delete_product(&id, force.unwrap_or(false))
+}
+
+// Semester rollover commands
+#[tauri::command]
+pub async fn rollover_class(database: State<'_, Database>, class_id: String, request: RolloverClassRequest) -> Result<ArchivedTerm, AppError> {
+    database.rollover_class(&class_id, request)
+}
+
+#[tauri::command]
+pub async fn get_archived_terms_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<ArchivedTerm>, AppError> {
+    database.get_archived_terms_by_class(&class_id)
+}
+
+#[tauri::command]
+pub async fn get_archived_term_detail(database: State<'_, Database>, term_id: String) -> Result<ArchivedTermDetail, AppError> {
+    database.get_archived_term_detail(&term_id)
+}
+
+// Integrity commands
+#[tauri::command]
+pub async fn check_integrity(database: State<'_, Database>) -> Result<IntegrityReport, AppError> {
+    database.check_integrity()
+}
+
+#[tauri::command]
+pub async fn repair_integrity(database: State<'_, Database>) -> Result<RepairIntegrityResult, AppError> {
+    database.repair_integrity()
+}
+
+// Backup commands
+#[tauri::command]
+pub async fn create_backup(database: State<'_, Database>) -> Result<BackupInfo, AppError> {
+    database.create_backup()
+}
+
+#[tauri::command]
+pub async fn list_backups(database: State<'_, Database>) -> Result<Vec<BackupInfo>, AppError> {
+    database.list_backups()
+}
+
+#[tauri::command]
+pub async fn restore_backup(database: State<'_, Database>, file_name: String) -> Result<RestoreBackupResult, AppError> {
+    database.restore_backup(&file_name)
+}
+
+#[tauri::command]
+pub async fn get_backup_settings(database: State<'_, Database>) -> Result<BackupSettings, AppError> {
+    database.get_backup_settings()
+}
+
+#[tauri::command]
+pub async fn update_backup_settings(database: State<'_, Database>, settings: BackupSettings) -> Result<BackupSettings, AppError> {
+    database.update_backup_settings(settings)
+}
+
+// Data location commands
+#[tauri::command]
+pub async fn get_data_location(app_handle: AppHandle, database: State<'_, Database>) -> Result<DataLocation, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    database.get_data_location(&config_dir)
+}
+
+#[tauri::command]
+pub async fn move_data_location(app_handle: AppHandle, database: State<'_, Database>, target_dir: String) -> Result<DataLocation, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    database.move_data_location(&config_dir, &target_dir)
+}
+
+#[tauri::command]
+pub async fn detect_legacy_databases(app_handle: AppHandle, database: State<'_, Database>) -> Result<Vec<DetectedDatabase>, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    let default_data_dir = app_handle.path().app_data_dir()?;
+    database.detect_legacy_databases(&config_dir, &default_data_dir)
+}
+
+// Class bundle commands
+#[tauri::command]
+pub async fn export_class_bundle(database: State<'_, Database>, class_id: String) -> Result<ClassBundle, AppError> {
+    database.export_class_bundle(&class_id)
+}
+
+#[tauri::command]
+pub async fn preview_class_bundle_import(database: State<'_, Database>, bundle: ClassBundle) -> Result<ClassBundleImportPreview, AppError> {
+    database.preview_class_bundle_import(&bundle)
+}
+
+#[tauri::command]
+pub async fn import_class_bundle(database: State<'_, Database>, request: ImportClassBundleRequest) -> Result<ImportClassBundleResult, AppError> {
+    database.import_class_bundle(request)
+}
+
+// Recycle bin commands
+#[tauri::command]
+pub async fn list_trash(database: State<'_, Database>) -> Result<Vec<TrashItem>, AppError> {
+    database.list_trash()
+}
+
+#[tauri::command]
+pub async fn restore_class(database: State<'_, Database>, id: String) -> Result<Class, AppError> {
+    database.restore_class(&id)
+}
+
+#[tauri::command]
+pub async fn restore_student(database: State<'_, Database>, id: String) -> Result<Student, AppError> {
+    database.restore_student(&id)
+}
+
+#[tauri::command]
+pub async fn restore_product(database: State<'_, Database>, id: String) -> Result<Product, AppError> {
+    database.restore_product(&id)
+}
+
+#[tauri::command]
+pub async fn purge_trash(database: State<'_, Database>, older_than_days: Option<i64>) -> Result<PurgeTrashResult, AppError> {
+    database.purge_trash(older_than_days)
 }
 
 // File operations
+fn save_export(app_handle: &AppHandle, target_dir: Option<String>, file: ExportFile) -> Result<ExportFileResult, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    let saved = file_save::save_file(&config_dir, target_dir.as_deref(), &file.file_name, &file.bytes)?;
+    Ok(ExportFileResult {
+        path: saved.path,
+        file_name: saved.file_name,
+        row_count: file.row_count as i32,
+    })
+}
+
 #[tauri::command]
-pub async fn save_file_to_desktop(filename: String, data: Vec<u8>) -> Result<String, String> {
-    // 获取桌面路径
-    let desktop_path = if let Some(home_dir) = dirs::home_dir() {
-        home_dir.join("Desktop")
-    } else {
-        return Err("无法获取桌面路径".to_string());
-    };
+pub async fn save_file(app_handle: AppHandle, file_name: String, data: Vec<u8>, target_dir: Option<String>) -> Result<SavedFile, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    file_save::save_file(&config_dir, target_dir.as_deref(), &file_name, &data)
+}
 
-    let file_path = desktop_path.join(filename);
+/// The folder a save without `target_dir` would use.
+#[tauri::command]
+pub async fn get_save_location(app_handle: AppHandle) -> Result<String, AppError> {
+    let config_dir = app_handle.path().app_config_dir()?;
+    let config = AppConfig::load(&config_dir)?;
+    file_save::resolve_save_dir(&config)
+        .map(|dir| dir.to_string_lossy().to_string())
+}
 
-    match fs::write(&file_path, data) {
-        Ok(_) => Ok(file_path.to_string_lossy().to_string()),
-        Err(e) => Err(format!("文件保存失败: {}", e))
-    }
+// Export commands
+#[tauri::command]
+pub async fn export_students(app_handle: AppHandle, database: State<'_, Database>, class_id: String, format: String, target_dir: Option<String>) -> Result<ExportFileResult, AppError> {
+    let file = database.export_students(&class_id, &format)?;
+    save_export(&app_handle, target_dir, file)
+}
+
+#[tauri::command]
+pub async fn export_products(app_handle: AppHandle, database: State<'_, Database>, class_id: String, format: String, target_dir: Option<String>) -> Result<ExportFileResult, AppError> {
+    let file = database.export_products(&class_id, &format)?;
+    save_export(&app_handle, target_dir, file)
+}
+
+#[tauri::command]
+pub async fn export_purchase_records(app_handle: AppHandle, database: State<'_, Database>, class_id: String, filter: ExportPurchaseRecordsRequest, format: String, target_dir: Option<String>) -> Result<ExportFileResult, AppError> {
+    let file = database.export_purchase_records(&class_id, &filter, &format)?;
+    save_export(&app_handle, target_dir, file)
+}
+
+#[tauri::command]
+pub async fn export_pick_list(app_handle: AppHandle, database: State<'_, Database>, class_id: Option<String>, format: String, target_dir: Option<String>) -> Result<ExportFileResult, AppError> {
+    let file = database.export_pick_list(class_id.as_deref(), &format)?;
+    save_export(&app_handle, target_dir, file)
 }
 
 // Purchase record commands
 #[tauri::command]
-pub async fn create_purchase_record(database: State<'_, Database>, request: CreatePurchaseRequest) -> Result<PurchaseRecord, String> {
+pub async fn create_purchase_record(database: State<'_, Database>, request: CreatePurchaseRequest) -> Result<PurchaseRecord, AppError> {
     database.create_purchase_record(request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_purchase_records_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<PurchaseRecord>, String> {
+pub async fn get_purchase_records_by_class(database: State<'_, Database>, class_id: String) -> Result<Vec<PurchaseRecord>, AppError> {
     database.get_purchase_records_by_class(&class_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_purchase_records_paginated(database: State<'_, Database>, class_id: String, page: i64, page_size: i64, source: Option<String>) -> Result<PaginatedPurchaseRecords, String> {
+pub async fn get_purchase_records_paginated(database: State<'_, Database>, class_id: String, page: i64, page_size: i64, source: Option<String>) -> Result<PaginatedPurchaseRecords, AppError> {
     database.get_purchase_records_paginated(&class_id, page, page_size, source.as_deref())
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn update_shipping_status(database: State<'_, Database>, record_id: String, request: UpdateShippingStatusRequest) -> Result<(), String> {
+pub async fn update_shipping_status(database: State<'_, Database>, record_id: String, request: UpdateShippingStatusRequest) -> Result<(), AppError> {
     database.update_shipping_status(&record_id, &request.shipping_status)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ship_purchase_records(database: State<'_, Database>, record_ids: Vec<String>) -> Result<BulkShippingResult, AppError> {
+    database.ship_purchase_records(&record_ids)
+}
+
+#[tauri::command]
+pub async fn ship_student_pending_records(database: State<'_, Database>, student_id: String) -> Result<BulkShippingResult, AppError> {
+    database.ship_student_pending_records(&student_id)
+}
+
+#[tauri::command]
+pub async fn ship_class_pending_records(database: State<'_, Database>, class_id: String) -> Result<BulkShippingResult, AppError> {
+    database.ship_class_pending_records(&class_id)
+}
+
+/// Omit `class_id` for a list across all classes.
+#[tauri::command]
+pub async fn get_pick_list(database: State<'_, Database>, class_id: Option<String>) -> Result<PickList, AppError> {
+    database.get_pick_list(class_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn refund_purchase_record(database: State<'_, Database>, record_id: String, request: RefundPurchaseRequest) -> Result<RefundPurchaseResult, AppError> {
+    database.refund_purchase_record(&record_id, request)
 }
 
 // Wheel commands
 #[tauri::command]
-pub async fn get_wheel_config(database: State<'_, Database>, class_id: String) -> Result<WheelConfig, String> {
+pub async fn get_wheel_config(database: State<'_, Database>, class_id: String) -> Result<WheelConfig, AppError> {
     database.get_wheel_config(&class_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn save_wheel_config(database: State<'_, Database>, class_id: String, request: SaveWheelConfigRequest) -> Result<WheelConfig, String> {
+pub async fn save_wheel_config(database: State<'_, Database>, class_id: String, request: SaveWheelConfigRequest) -> Result<WheelConfig, AppError> {
     database.save_wheel_config(&class_id, request)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn spin_wheel(database: State<'_, Database>, class_id: String, request: SpinWheelRequest) -> Result<SpinWheelResult, String> {
+pub async fn spin_wheel(database: State<'_, Database>, class_id: String, request: SpinWheelRequest) -> Result<SpinWheelResult, AppError> {
     database.spin_wheel(&class_id, request)
-        .map_err(|e| e.to_string())
+}
+
+// Audit log commands
+#[tauri::command]
+pub async fn get_audit_logs_paginated(database: State<'_, Database>, page: i64, page_size: i64, class_id: Option<String>, entity_type: Option<String>, entity_id: Option<String>) -> Result<PaginatedAuditLogs, AppError> {
+    database.get_audit_logs_paginated(page, page_size, class_id.as_deref(), entity_type.as_deref(), entity_id.as_deref())
+}
+
+// Schema commands
+#[tauri::command]
+pub async fn get_schema_info(database: State<'_, Database>) -> Result<SchemaInfo, AppError> {
+    database.get_schema_info()
 }
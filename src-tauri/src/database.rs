@@ -1,11 +1,18 @@
-use std::sync::Mutex;
-use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::path::{Path, PathBuf};
 use rusqlite::{Connection, Result as SqliteResult, params};
-use rusqlite::OptionalExtension;
-use chrono::{DateTime, Utc};
+use rusqlite::{DatabaseName, OpenFlags, OptionalExtension};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
 use uuid::Uuid;
 use tauri::{AppHandle, Manager};
 
+use crate::error::AppError;
+use crate::migrations;
+use crate::backup;
+use crate::app_config::AppConfig;
+use crate::student_import::{self, StudentImportRow};
+use crate::export::{self, ExportCell, ExportFile, ExportTable};
+
 use crate::models::{
     Class,
     Student,
@@ -24,1222 +31,5244 @@ use crate::models::{
     SaveWheelConfigRequest,
     SpinWheelResult,
     SpinWheelRequest,
+    SchemaInfo,
+    AppliedMigration,
+    PointTransaction,
+    PaginatedPointTransactions,
+    PointAdjustmentResult,
+    PointSettings,
+    PointReason,
+    CreatePointReasonRequest,
+    UpdatePointReasonRequest,
+    BatchAdjustPointsRequest,
+    BatchAdjustPointsResult,
+    AuditLog,
+    PaginatedAuditLogs,
+    TrashItem,
+    PurgeTrashResult,
+    ClassDeletionPreview,
+    RolloverClassRequest,
+    ArchivedTerm,
+    TermStudentBalance,
+    ArchivedTermDetail,
+    TransferStudentRequest,
+    StudentTransfer,
+    TransferStudentResult,
+    IntegrityIssue,
+    IntegrityReport,
+    IntegrityRepair,
+    RepairIntegrityResult,
+    BackupInfo,
+    BackupSettings,
+    RestoreBackupResult,
+    DataLocation,
+    DetectedDatabase,
+    ClassBundle,
+    BundleWheelConfig,
+    ClassBundleImportPreview,
+    ImportClassBundleRequest,
+    ImportClassBundleResult,
+    StudentImportError,
+    StudentImportReport,
+    StudentFieldChange,
+    StudentImportPlanRow,
+    StudentImportPreview,
+    CommitStudentImportRequest,
+    CommitStudentImportResult,
+    ExportPurchaseRecordsRequest,
+    RefundPurchaseRequest,
+    RefundPurchaseResult,
+    BulkShippingResult,
+    PickList,
+    PickListProduct,
+    PickListStudent,
 };
 
-pub struct Database {
-    pub conn: Mutex<Connection>,
+// Values stored in point_transactions.source
+pub const POINT_SOURCE_MANUAL: &str = "manual";
+pub const POINT_SOURCE_PURCHASE: &str = "purchase";
+pub const POINT_SOURCE_WHEEL: &str = "wheel";
+pub const POINT_SOURCE_UNDO: &str = "undo";
+pub const POINT_SOURCE_ROLLOVER: &str = "rollover";
+pub const POINT_SOURCE_IMPORT: &str = "import";
+pub const POINT_SOURCE_REFUND: &str = "refund";
+// Opening and carried-forward balances; never offered for undo
+pub const POINT_SOURCE_OPENING: &str = "opening";
+
+// Values stored in purchase_records.shipping_status
+pub const SHIPPING_STATUS_PENDING: &str = "pending";
+pub const SHIPPING_STATUS_READY: &str = "ready";
+pub const SHIPPING_STATUS_SHIPPED: &str = "shipped";
+pub const SHIPPING_STATUS_RECEIVED: &str = "received";
+pub const SHIPPING_STATUS_CANCELLED: &str = "cancelled";
+// Set only by refund_purchase_record, once every unit has been refunded
+pub const SHIPPING_STATUS_REFUNDED: &str = "refunded";
+
+/// Statuses `update_shipping_status` can set.
+pub const SHIPPING_STATUSES: &[&str] = &[
+    SHIPPING_STATUS_PENDING,
+    SHIPPING_STATUS_READY,
+    SHIPPING_STATUS_SHIPPED,
+    SHIPPING_STATUS_RECEIVED,
+    SHIPPING_STATUS_CANCELLED,
+];
+
+/// Fulfilment only moves forward: pending → ready → shipped → received. Before it ships a
+/// record can go back to pending or be cancelled, which refunds it. Received, cancelled and
+/// refunded are final.
+fn shipping_transition_allowed(from: &str, to: &str) -> bool {
+    matches!(
+        (from, to),
+        (SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY | SHIPPING_STATUS_SHIPPED | SHIPPING_STATUS_CANCELLED)
+            | (SHIPPING_STATUS_READY, SHIPPING_STATUS_PENDING | SHIPPING_STATUS_SHIPPED | SHIPPING_STATUS_CANCELLED)
+            | (SHIPPING_STATUS_SHIPPED, SHIPPING_STATUS_RECEIVED)
+    )
 }
 
-impl Database {
-    pub fn new(app_handle: &AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
-        let db_dir = Self::find_best_data_location(app_handle)?;
-        std::fs::create_dir_all(&db_dir)?;
+fn check_shipping_transition(from: &str, to: &str) -> Result<(), AppError> {
+    if !shipping_transition_allowed(from, to) {
+        return Err(AppError::conflict(format!(
+            "不能将发货状态从“{}”改为“{}”",
+            export::shipping_status_label(from),
+            export::shipping_status_label(to)
+        )));
+    }
+    Ok(())
+}
 
-        let db_path = db_dir.join("pointhub.db");
-        let conn = Connection::open(db_path)?;
+/// Maps the legacy `delivered` value, which may still arrive in old class bundles.
+fn normalize_shipping_status(status: &str) -> &str {
+    match status {
+        "delivered" => SHIPPING_STATUS_SHIPPED,
+        other => other,
+    }
+}
 
-        let database = Database {
-            conn: Mutex::new(conn),
-        };
+/// One row to append to the points ledger.
+struct PointChange<'a> {
+    student_id: &'a str,
+    class_id: &'a str,
+    delta: i32,
+    balance_after: i32,
+    reason: Option<&'a str>,
+    source: &'a str,
+    related_id: Option<&'a str>,
+    reason_id: Option<&'a str>,
+}
 
-        database.init_tables()?;
-        database.init_sample_data()?;
+fn record_point_transaction(conn: &Connection, change: PointChange) -> SqliteResult<PointTransaction> {
+    let id = Uuid::new_v4().to_string();
+    let created_at = Utc::now();
 
-        Ok(database)
-    }
+    conn.execute(
+        "INSERT INTO point_transactions (id, student_id, class_id, delta, balance_after, reason, source, related_id, reason_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            id,
+            change.student_id,
+            change.class_id,
+            change.delta,
+            change.balance_after,
+            change.reason,
+            change.source,
+            change.related_id,
+            change.reason_id,
+            created_at.to_rfc3339()
+        ],
+    )?;
+
+    Ok(PointTransaction {
+        id,
+        student_id: change.student_id.to_string(),
+        class_id: change.class_id.to_string(),
+        delta: change.delta,
+        balance_after: change.balance_after,
+        reason: change.reason.map(|reason| reason.to_string()),
+        source: change.source.to_string(),
+        related_id: change.related_id.map(|related_id| related_id.to_string()),
+        reason_id: change.reason_id.map(|reason_id| reason_id.to_string()),
+        reverts_id: None,
+        reverted_by: None,
+        operation_id: None,
+        created_at,
+    })
+}
 
-    fn find_best_data_location(app_handle: &AppHandle) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let possible_dirs = vec![
-            PathBuf::from("D:\\PointHub"),
-            PathBuf::from("E:\\PointHub"),
-            PathBuf::from("F:\\PointHub"),
-            app_handle.path().app_data_dir()?,
-        ];
+fn row_to_point_transaction(row: &rusqlite::Row) -> SqliteResult<PointTransaction> {
+    let created_at_str: String = row.get(8)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(PointTransaction {
+        id: row.get(0)?,
+        student_id: row.get(1)?,
+        class_id: row.get(2)?,
+        delta: row.get(3)?,
+        balance_after: row.get(4)?,
+        reason: row.get(5)?,
+        source: row.get(6)?,
+        related_id: row.get(7)?,
+        reason_id: row.get(9)?,
+        reverts_id: row.get(10)?,
+        reverted_by: row.get(11)?,
+        operation_id: row.get(12)?,
+        created_at,
+    })
+}
 
-        for dir in possible_dirs {
-            if let Some(parent) = dir.parent() {
-                if parent.exists() {
-                    return Ok(dir);
-                }
-            }
-        }
+// Values stored in audit_logs.entity_type
+const AUDIT_ENTITY_CLASS: &str = "class";
+const AUDIT_ENTITY_STUDENT: &str = "student";
+const AUDIT_ENTITY_PRODUCT: &str = "product";
+const AUDIT_ENTITY_PURCHASE_RECORD: &str = "purchase_record";
+const AUDIT_ENTITY_WHEEL_CONFIG: &str = "wheel_config";
+const AUDIT_ENTITY_POINT_REASON: &str = "point_reason";
+const AUDIT_ENTITY_SETTING: &str = "setting";
+const AUDIT_ENTITY_ARCHIVED_TERM: &str = "archived_term";
+const AUDIT_ENTITY_DATABASE: &str = "database";
+
+// Values stored in audit_logs.action
+const AUDIT_ACTION_CREATE: &str = "create";
+const AUDIT_ACTION_UPDATE: &str = "update";
+const AUDIT_ACTION_DELETE: &str = "delete";
+const AUDIT_ACTION_ADJUST_POINTS: &str = "adjust_points";
+const AUDIT_ACTION_UNDO_POINTS: &str = "undo_points";
+const AUDIT_ACTION_RESTORE: &str = "restore";
+const AUDIT_ACTION_PURGE: &str = "purge";
+const AUDIT_ACTION_TRANSFER: &str = "transfer";
+const AUDIT_ACTION_REPAIR: &str = "repair";
+const AUDIT_ACTION_IMPORT: &str = "import";
+const AUDIT_ACTION_REFUND: &str = "refund";
+const AUDIT_ACTION_BACKUP_FAILED: &str = "backup_failed";
+
+/// One row to append to the audit log. `before`/`after` are JSON snapshots of the entity.
+struct AuditEntry<'a> {
+    entity_type: &'a str,
+    entity_id: &'a str,
+    class_id: Option<&'a str>,
+    action: &'a str,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
 
-        Ok(app_handle.path().app_data_dir()?)
-    }
+fn to_json<T: serde::Serialize>(value: &T) -> Option<serde_json::Value> {
+    serde_json::to_value(value).ok()
+}
 
-    fn init_tables(&self) -> SqliteResult<()> {
-        let mut conn = self.conn.lock().unwrap();
+fn write_audit_log(conn: &Connection, entry: AuditEntry) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO audit_logs (id, entity_type, entity_id, class_id, action, before_json, after_json, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            Uuid::new_v4().to_string(),
+            entry.entity_type,
+            entry.entity_id,
+            entry.class_id,
+            entry.action,
+            entry.before.map(|value| value.to_string()),
+            entry.after.map(|value| value.to_string()),
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS classes (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                description TEXT,
-                student_count INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
+fn row_to_audit_log(row: &rusqlite::Row) -> SqliteResult<AuditLog> {
+    let created_at_str: String = row.get(7)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+    let before_json: Option<String> = row.get(5)?;
+    let after_json: Option<String> = row.get(6)?;
+
+    Ok(AuditLog {
+        id: row.get(0)?,
+        entity_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        class_id: row.get(3)?,
+        action: row.get(4)?,
+        before: before_json.and_then(|json| serde_json::from_str(&json).ok()),
+        after: after_json.and_then(|json| serde_json::from_str(&json).ok()),
+        created_at,
+    })
+}
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS students (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                student_number TEXT NOT NULL DEFAULT '',
-                points INTEGER NOT NULL DEFAULT 0,
-                class_id TEXT NOT NULL,
-                class_name TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(class_id) REFERENCES classes(id)
-            )",
-            [],
-        )?;
+fn row_to_class(row: &rusqlite::Row) -> SqliteResult<Class> {
+    let created_at_str: String = row.get(4)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(Class {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        student_count: row.get(3)?,
+        created_at,
+    })
+}
 
-        // Add created_at column to existing students table if it doesn't exist
-        let _ = conn.execute(
-            "ALTER TABLE students ADD COLUMN created_at TEXT DEFAULT ''",
-            [],
-        );
-
-        // Add student_number column to existing students table if it doesn't exist
-        let _ = conn.execute(
-            "ALTER TABLE students ADD COLUMN student_number TEXT DEFAULT ''",
-            [],
-        );
-
-        // Update any existing students without created_at
-        conn.execute(
-            "UPDATE students SET created_at = ? WHERE created_at = '' OR created_at IS NULL",
-            [&Utc::now().to_rfc3339()],
-        )?;
+fn row_to_student(row: &rusqlite::Row) -> SqliteResult<Student> {
+    let created_at_str: String = row.get(6)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(Student {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        student_number: row.get(2)?,
+        points: row.get(3)?,
+        class_id: row.get(4)?,
+        class_name: row.get(5)?,
+        created_at,
+    })
+}
 
-        // Update any existing students without student_number (generate default)
-        conn.execute(
-            "UPDATE students SET student_number = 'STU' || substr(id, 1, 8) WHERE student_number = '' OR student_number IS NULL",
-            [],
-        )?;
+fn row_to_product(row: &rusqlite::Row) -> SqliteResult<Product> {
+    let created_at_str: String = row.get(5)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(Product {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        points: row.get(2)?,
+        stock: row.get(3)?,
+        class_id: row.get(4)?,
+        created_at,
+    })
+}
 
-        // Create products table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS products (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                points INTEGER NOT NULL,
-                stock INTEGER NOT NULL,
-                class_id TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(class_id) REFERENCES classes(id)
-            )",
-            [],
-        )?;
+fn row_to_purchase_record(row: &rusqlite::Row) -> SqliteResult<PurchaseRecord> {
+    Ok(PurchaseRecord {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        product_name: row.get(2)?,
+        points: row.get(3)?,
+        student_id: row.get(4)?,
+        student_name: row.get(5)?,
+        quantity: row.get(6)?,
+        class_id: row.get(7)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).map_err(|_| rusqlite::Error::InvalidColumnType(8, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
+        shipping_status: row.get(9)?,
+        source: row.get(10)?,
+        refunded_quantity: row.get(11)?,
+        shipped_at: optional_time(row.get(12)?),
+        received_at: optional_time(row.get(13)?),
+    })
+}
 
-        // Create purchase_records table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS purchase_records (
-                id TEXT PRIMARY KEY,
-                product_id TEXT NOT NULL,
-                product_name TEXT NOT NULL,
-                points INTEGER NOT NULL,
-                student_id TEXT NOT NULL,
-                student_name TEXT NOT NULL,
-                quantity INTEGER NOT NULL,
-                class_id TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                shipping_status TEXT NOT NULL DEFAULT 'pending',
-                source TEXT NOT NULL DEFAULT '购买',
-                FOREIGN KEY(student_id) REFERENCES students(id),
-                FOREIGN KEY(class_id) REFERENCES classes(id)
-            )",
-            [],
-        )?;
+fn optional_time(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
 
-        // Add shipping_status column to existing purchase_records table if it doesn't exist
-        let _ = conn.execute(
-            "ALTER TABLE purchase_records ADD COLUMN shipping_status TEXT DEFAULT 'pending'",
-            [],
-        );
-
-        // Add source column to existing purchase_records table if it doesn't exist
-        let _ = conn.execute(
-            "ALTER TABLE purchase_records ADD COLUMN source TEXT DEFAULT '购买'",
-            [],
-        );
-
-        // Backfill source for historical records
-        conn.execute(
-            "UPDATE purchase_records SET source = '购买' WHERE source = '' OR source IS NULL",
-            [],
-        )?;
+fn query_class(conn: &Connection, id: &str) -> SqliteResult<Option<Class>> {
+    conn.query_row(
+        "SELECT id, name, description, student_count, created_at FROM classes WHERE id = ?",
+        [id],
+        row_to_class,
+    )
+    .optional()
+}
 
-        // One wheel config per class
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS wheel_configs (
-                class_id TEXT PRIMARY KEY,
-                spin_cost INTEGER NOT NULL DEFAULT 10,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY(class_id) REFERENCES classes(id)
-            )",
-            [],
-        )?;
+fn query_student(conn: &Connection, id: &str) -> SqliteResult<Option<Student>> {
+    conn.query_row(
+        "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ?",
+        [id],
+        row_to_student,
+    )
+    .optional()
+}
 
-        // Wheel slots control probability via duplicated slots.
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS wheel_slots (
-                id TEXT PRIMARY KEY,
-                class_id TEXT NOT NULL,
-                product_id TEXT NOT NULL,
-                slot_index INTEGER NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(class_id) REFERENCES classes(id),
-                FOREIGN KEY(product_id) REFERENCES products(id)
-            )",
-            [],
-        )?;
+fn query_product(conn: &Connection, id: &str) -> SqliteResult<Option<Product>> {
+    conn.query_row(
+        "SELECT id, name, points, stock, class_id, created_at FROM products WHERE id = ?",
+        [id],
+        row_to_product,
+    )
+    .optional()
+}
 
-        // Rebuild purchase_records table if old schema still contains product_id foreign key
-        if let Some(schema_sql) = conn
-            .query_row(
-                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'purchase_records'",
-                [],
-                |row| row.get::<_, String>(0),
-            )
-            .optional()? 
-        {
-            if schema_sql.contains("FOREIGN KEY(\"product_id\")")
-                || schema_sql.contains("FOREIGN KEY(product_id)")
-            {
-                conn.execute("PRAGMA foreign_keys = OFF", [])?;
-
-                let migration_result: SqliteResult<()> = (|| {
-                    let tx = conn.transaction()?;
-                    tx.execute("DROP TABLE IF EXISTS purchase_records_new", [])?;
-                    tx.execute(
-                        "CREATE TABLE purchase_records_new (
-                            id TEXT PRIMARY KEY,
-                            product_id TEXT NOT NULL,
-                            product_name TEXT NOT NULL,
-                            points INTEGER NOT NULL,
-                            student_id TEXT NOT NULL,
-                            student_name TEXT NOT NULL,
-                            quantity INTEGER NOT NULL,
-                            class_id TEXT NOT NULL,
-                            created_at TEXT NOT NULL,
-                            shipping_status TEXT NOT NULL DEFAULT 'pending',
-                            source TEXT NOT NULL DEFAULT '购买',
-                            FOREIGN KEY(student_id) REFERENCES students(id),
-                            FOREIGN KEY(class_id) REFERENCES classes(id)
-                        )",
-                        [],
-                    )?;
+/// The mutation-path lookups: a row in the recycle bin is reported as not found, so it
+/// cannot be edited until it is restored.
+fn live_class(conn: &Connection, id: &str) -> Result<Class, AppError> {
+    conn.query_row(
+        "SELECT id, name, description, student_count, created_at FROM classes WHERE id = ? AND deleted_at IS NULL",
+        [id],
+        row_to_class,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("班级不存在"))
+}
 
-                    tx.execute(
-                        "INSERT INTO purchase_records_new (
-                            id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source
-                        )
-                        SELECT
-                            id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, COALESCE(shipping_status, 'pending'), COALESCE(source, '购买')
-                        FROM purchase_records",
-                        [],
-                    )?;
+fn live_student(conn: &Connection, id: &str) -> Result<Student, AppError> {
+    conn.query_row(
+        "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ? AND deleted_at IS NULL",
+        [id],
+        row_to_student,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("学生不存在"))
+}
 
-                    tx.execute("DROP TABLE purchase_records", [])?;
-                    tx.execute(
-                        "ALTER TABLE purchase_records_new RENAME TO purchase_records",
-                        [],
-                    )?;
-                    tx.commit()?;
-                    Ok(())
-                })();
+fn live_product(conn: &Connection, id: &str) -> Result<Product, AppError> {
+    conn.query_row(
+        "SELECT id, name, points, stock, class_id, created_at FROM products WHERE id = ? AND deleted_at IS NULL",
+        [id],
+        row_to_product,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("商品不存在"))
+}
 
-                conn.execute("PRAGMA foreign_keys = ON", [])?;
-                migration_result?;
-            }
-        }
+fn query_purchase_record(conn: &Connection, id: &str) -> SqliteResult<Option<PurchaseRecord>> {
+    conn.query_row(
+        "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at FROM purchase_records WHERE id = ?",
+        [id],
+        row_to_purchase_record,
+    )
+    .optional()
+}
 
-        Ok(())
-    }
+fn refresh_student_count(conn: &Connection, class_id: &str) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ?1 AND deleted_at IS NULL) WHERE id = ?1",
+        [class_id],
+    )?;
+    Ok(())
+}
 
-    fn init_sample_data(&self) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+/// Permanently removes a class and everything that belongs to it. Children go first so
+/// the foreign keys on students, wheel_slots and purchase_records never dangle.
+fn purge_class(conn: &Connection, class_id: &str) -> SqliteResult<()> {
+    conn.execute(
+        "DELETE FROM term_purchase_records WHERE term_id IN (SELECT id FROM archived_terms WHERE class_id = ?)",
+        [class_id],
+    )?;
+    conn.execute(
+        "DELETE FROM term_student_balances WHERE term_id IN (SELECT id FROM archived_terms WHERE class_id = ?)",
+        [class_id],
+    )?;
+    conn.execute("DELETE FROM archived_terms WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM wheel_slots WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM wheel_configs WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM purchase_records WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM point_transactions WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM point_reasons WHERE class_id = ?", [class_id])?;
+    conn.execute(
+        "DELETE FROM student_transfers WHERE student_id IN (SELECT id FROM students WHERE class_id = ?)",
+        [class_id],
+    )?;
+    conn.execute("DELETE FROM students WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM products WHERE class_id = ?", [class_id])?;
+    conn.execute("DELETE FROM classes WHERE id = ?", [class_id])?;
+    Ok(())
+}
 
-        // Check if data already exists
-        let count: i32 = conn.query_row("SELECT COUNT(*) FROM classes", [], |row| row.get(0))?;
-        if count > 0 {
-            return Ok(());
-        }
+fn purge_student(conn: &Connection, student_id: &str) -> SqliteResult<()> {
+    conn.execute("DELETE FROM purchase_records WHERE student_id = ?", [student_id])?;
+    conn.execute("DELETE FROM point_transactions WHERE student_id = ?", [student_id])?;
+    conn.execute("DELETE FROM student_transfers WHERE student_id = ?", [student_id])?;
+    conn.execute("DELETE FROM students WHERE id = ?", [student_id])?;
+    Ok(())
+}
 
-        // Insert sample class
-        let class_id = Uuid::new_v4().to_string();
-        let now = Utc::now().to_rfc3339();
+fn purge_product(conn: &Connection, product_id: &str) -> SqliteResult<()> {
+    conn.execute("DELETE FROM wheel_slots WHERE product_id = ?", [product_id])?;
+    conn.execute("DELETE FROM products WHERE id = ?", [product_id])?;
+    Ok(())
+}
 
-        conn.execute(
-            "INSERT INTO classes (id, name, description, student_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                class_id,
-                "计算机科学与技术2021级1班",
-                "计算机科学与技术专业",
-                1,
-                now
-            ],
-        )?;
+/// Spin cost and slot layout of a class's wheel, as recorded in the audit log.
+fn wheel_config_snapshot(conn: &Connection, class_id: &str) -> SqliteResult<Option<serde_json::Value>> {
+    let spin_cost: Option<i32> = conn
+        .query_row("SELECT spin_cost FROM wheel_configs WHERE class_id = ?", [class_id], |row| row.get(0))
+        .optional()?;
 
-        // Insert sample student
-        let student_id = Uuid::new_v4().to_string();
-        conn.execute(
-            "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                student_id,
-                "张三",
-                "2021001",
-                85,
-                class_id,
-                "计算机科学与技术2021级1班",
-                now
-            ],
+    let spin_cost = match spin_cost {
+        Some(spin_cost) => spin_cost,
+        None => return Ok(None),
+    };
+
+    let mut stmt = conn.prepare("SELECT product_id FROM wheel_slots WHERE class_id = ? ORDER BY slot_index ASC")?;
+    let product_iter = stmt.query_map([class_id], |row| row.get::<_, String>(0))?;
+
+    let mut product_ids = Vec::new();
+    for product_id in product_iter {
+        product_ids.push(product_id?);
+    }
+
+    Ok(Some(serde_json::json!({
+        "spinCost": spin_cost,
+        "productIds": product_ids,
+    })))
+}
+
+// History policies accepted by transfer_student
+const TRANSFER_HISTORY_CARRY: &str = "carry";
+const TRANSFER_HISTORY_SPLIT: &str = "split";
+
+/// Moves a student into another class. With `carry` the student's purchase records and
+/// ledger rows follow them; with `split` they stay behind as part of the old class's history.
+fn move_student(
+    conn: &Connection,
+    student: &Student,
+    to_class_id: &str,
+    history_policy: &str,
+) -> Result<StudentTransfer, AppError> {
+    if history_policy != TRANSFER_HISTORY_CARRY && history_policy != TRANSFER_HISTORY_SPLIT {
+        return Err(AppError::invalid_input("无效的历史记录处理方式"));
+    }
+    if to_class_id == student.class_id {
+        return Err(AppError::conflict("学生已在该班级中"));
+    }
+
+    let class_name: String = conn
+        .query_row(
+            "SELECT name FROM classes WHERE id = ? AND deleted_at IS NULL",
+            [to_class_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("目标班级不存在"))?;
+
+    conn.execute(
+        "UPDATE students SET class_id = ?1, class_name = ?2 WHERE id = ?3",
+        params![to_class_id, class_name, student.id],
+    )?;
+
+    let (purchase_records_moved, point_transactions_moved) = if history_policy == TRANSFER_HISTORY_CARRY {
+        let purchase_records_moved = conn.execute(
+            "UPDATE purchase_records SET class_id = ?1 WHERE student_id = ?2 AND class_id = ?3",
+            params![to_class_id, student.id, student.class_id],
+        )?;
+        let point_transactions_moved = conn.execute(
+            "UPDATE point_transactions SET class_id = ?1 WHERE student_id = ?2 AND class_id = ?3",
+            params![to_class_id, student.id, student.class_id],
         )?;
+        (purchase_records_moved as i32, point_transactions_moved as i32)
+    } else {
+        (0, 0)
+    };
+
+    refresh_student_count(conn, &student.class_id)?;
+    refresh_student_count(conn, to_class_id)?;
+
+    let transfer = StudentTransfer {
+        id: Uuid::new_v4().to_string(),
+        student_id: student.id.clone(),
+        from_class_id: student.class_id.clone(),
+        to_class_id: to_class_id.to_string(),
+        history_policy: history_policy.to_string(),
+        purchase_records_moved,
+        point_transactions_moved,
+        created_at: Utc::now(),
+    };
+
+    conn.execute(
+        "INSERT INTO student_transfers (id, student_id, from_class_id, to_class_id, history_policy, purchase_records_moved, point_transactions_moved, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            transfer.id,
+            transfer.student_id,
+            transfer.from_class_id,
+            transfer.to_class_id,
+            transfer.history_policy,
+            transfer.purchase_records_moved,
+            transfer.point_transactions_moved,
+            transfer.created_at.to_rfc3339()
+        ],
+    )?;
+
+    Ok(transfer)
+}
 
-        Ok(())
+// Balance policies accepted by rollover_class
+const ROLLOVER_POLICY_RESET: &str = "reset";
+const ROLLOVER_POLICY_CARRY: &str = "carry";
+const ROLLOVER_POLICY_PERCENT: &str = "percent";
+
+fn row_to_archived_term(row: &rusqlite::Row) -> SqliteResult<ArchivedTerm> {
+    let wheel_config_json: Option<String> = row.get(8)?;
+    let period_start_str: Option<String> = row.get(9)?;
+    let archived_at_str: String = row.get(10)?;
+
+    Ok(ArchivedTerm {
+        id: row.get(0)?,
+        class_id: row.get(1)?,
+        name: row.get(2)?,
+        balance_policy: row.get(3)?,
+        carry_percent: row.get(4)?,
+        student_count: row.get(5)?,
+        total_points: row.get(6)?,
+        purchase_count: row.get(7)?,
+        wheel_config: wheel_config_json.and_then(|json| serde_json::from_str(&json).ok()),
+        period_start: period_start_str
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        archived_at: DateTime::parse_from_rfc3339(&archived_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+const ARCHIVED_TERM_COLUMNS: &str =
+    "id, class_id, name, balance_policy, carry_percent, student_count, total_points, purchase_count, wheel_config_json, period_start, archived_at";
+
+/// One consistency rule. `ids_sql` selects the ids of offending rows; `repair_sql`, when
+/// present, fixes exactly those rows and may embed the selection through `{ids}`.
+struct IntegrityCheck {
+    code: &'static str,
+    description: &'static str,
+    ids_sql: &'static str,
+    repair_sql: Option<&'static str>,
+}
+
+const INTEGRITY_SAMPLE_SIZE: usize = 10;
+
+const INTEGRITY_CHECKS: &[IntegrityCheck] = &[
+    IntegrityCheck {
+        code: "class_student_count",
+        description: "班级学生数与实际学生数不一致",
+        ids_sql: "SELECT c.id FROM classes c
+                  WHERE c.student_count <> (SELECT COUNT(*) FROM students s WHERE s.class_id = c.id AND s.deleted_at IS NULL)",
+        repair_sql: Some(
+            "UPDATE classes
+             SET student_count = (SELECT COUNT(*) FROM students s WHERE s.class_id = classes.id AND s.deleted_at IS NULL)
+             WHERE id IN ({ids})",
+        ),
+    },
+    IntegrityCheck {
+        code: "student_class_name",
+        description: "学生记录中的班级名称与班级不一致",
+        ids_sql: "SELECT s.id FROM students s JOIN classes c ON c.id = s.class_id WHERE s.class_name <> c.name",
+        repair_sql: Some(
+            "UPDATE students SET class_name = (SELECT name FROM classes WHERE id = students.class_id) WHERE id IN ({ids})",
+        ),
+    },
+    IntegrityCheck {
+        code: "purchase_product_name",
+        description: "购买记录中的商品名称与商品不一致",
+        ids_sql: "SELECT r.id FROM purchase_records r JOIN products p ON p.id = r.product_id WHERE r.product_name <> p.name",
+        repair_sql: Some(
+            "UPDATE purchase_records SET product_name = (SELECT name FROM products WHERE id = purchase_records.product_id) WHERE id IN ({ids})",
+        ),
+    },
+    IntegrityCheck {
+        code: "purchase_student_name",
+        description: "购买记录中的学生姓名与学生不一致",
+        ids_sql: "SELECT r.id FROM purchase_records r JOIN students s ON s.id = r.student_id WHERE r.student_name <> s.name",
+        repair_sql: Some(
+            "UPDATE purchase_records SET student_name = (SELECT name FROM students WHERE id = purchase_records.student_id) WHERE id IN ({ids})",
+        ),
+    },
+    IntegrityCheck {
+        code: "wheel_slot_dangling",
+        description: "转盘格子指向不存在、已删除或其他班级的商品",
+        // A product trashed together with its class comes back with it, so its slots are kept
+        ids_sql: "SELECT w.id FROM wheel_slots w
+                  LEFT JOIN classes c ON c.id = w.class_id
+                  LEFT JOIN products p ON p.id = w.product_id
+                  WHERE p.id IS NULL OR p.class_id <> w.class_id
+                     OR (p.deleted_at IS NOT NULL AND p.deleted_at IS NOT c.deleted_at)",
+        repair_sql: Some("DELETE FROM wheel_slots WHERE id IN ({ids})"),
+    },
+    IntegrityCheck {
+        code: "point_transaction_reason_dangling",
+        description: "积分流水引用的积分原因不存在",
+        ids_sql: "SELECT t.id FROM point_transactions t LEFT JOIN point_reasons r ON r.id = t.reason_id
+                  WHERE t.reason_id IS NOT NULL AND r.id IS NULL",
+        repair_sql: Some("UPDATE point_transactions SET reason_id = NULL WHERE id IN ({ids})"),
+    },
+    IntegrityCheck {
+        code: "student_missing_class",
+        description: "学生所属班级不存在",
+        ids_sql: "SELECT s.id FROM students s LEFT JOIN classes c ON c.id = s.class_id WHERE c.id IS NULL",
+        repair_sql: None,
+    },
+    IntegrityCheck {
+        code: "product_missing_class",
+        description: "商品所属班级不存在",
+        ids_sql: "SELECT p.id FROM products p LEFT JOIN classes c ON c.id = p.class_id WHERE c.id IS NULL",
+        repair_sql: None,
+    },
+    IntegrityCheck {
+        code: "purchase_missing_student",
+        description: "购买记录引用的学生不存在",
+        ids_sql: "SELECT r.id FROM purchase_records r LEFT JOIN students s ON s.id = r.student_id WHERE s.id IS NULL",
+        repair_sql: None,
+    },
+    IntegrityCheck {
+        code: "purchase_missing_product",
+        description: "购买记录引用的商品不存在",
+        ids_sql: "SELECT r.id FROM purchase_records r LEFT JOIN products p ON p.id = r.product_id WHERE p.id IS NULL",
+        repair_sql: None,
+    },
+    IntegrityCheck {
+        code: "point_balance_mismatch",
+        description: "学生积分与积分流水最后余额不一致",
+        ids_sql: "SELECT s.id FROM students s
+                  WHERE s.points <> COALESCE(
+                      (SELECT t.balance_after FROM point_transactions t WHERE t.student_id = s.id ORDER BY t.created_at DESC, t.rowid DESC LIMIT 1),
+                      s.points
+                  )",
+        repair_sql: None,
+    },
+];
+
+fn collect_strings(conn: &Connection, sql: &str) -> SqliteResult<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let value_iter = stmt.query_map([], |row| row.get(0))?;
+
+    let mut values = Vec::new();
+    for value in value_iter {
+        values.push(value?);
     }
+    Ok(values)
+}
 
-    // Class CRUD operations
-    pub fn get_all_classes(&self) -> Result<Vec<Class>, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, description, student_count, created_at FROM classes ORDER BY created_at DESC")?;
+fn integrity_issue(code: &str, description: &str, ids: Vec<String>, repairable: bool) -> IntegrityIssue {
+    IntegrityIssue {
+        code: code.to_string(),
+        description: description.to_string(),
+        count: ids.len() as i64,
+        sample_ids: ids.into_iter().take(INTEGRITY_SAMPLE_SIZE).collect(),
+        repairable,
+    }
+}
 
-        let class_iter = stmt.query_map([], |row| {
-            let created_at_str: String = row.get(4)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+/// Runs SQLite's own checks followed by every rule in `INTEGRITY_CHECKS`.
+fn run_integrity_checks(conn: &Connection) -> SqliteResult<IntegrityReport> {
+    let mut issues = Vec::new();
 
-            Ok(Class {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                student_count: row.get(3)?,
-                created_at,
-            })
+    let messages: Vec<String> = collect_strings(conn, "PRAGMA integrity_check")?
+        .into_iter()
+        .filter(|message| message != "ok")
+        .collect();
+    if !messages.is_empty() {
+        issues.push(integrity_issue("sqlite_integrity", "SQLite 完整性检查未通过", messages, false));
+    }
+
+    // foreign_key_check yields (table, rowid, parent, fkid)
+    let violations: Vec<String> = {
+        let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+        let violation_iter = stmt.query_map([], |row| {
+            let table: String = row.get(0)?;
+            let rowid: Option<i64> = row.get(1)?;
+            let parent: String = row.get(2)?;
+            Ok(format!("{}:{}->{}", table, rowid.unwrap_or_default(), parent))
         })?;
 
-        let mut classes = Vec::new();
-        for class in class_iter {
-            classes.push(class?);
+        let mut violations = Vec::new();
+        for violation in violation_iter {
+            violations.push(violation?);
         }
+        violations
+    };
+    if !violations.is_empty() {
+        issues.push(integrity_issue("foreign_key_violation", "存在违反外键约束的记录", violations, false));
+    }
 
-        Ok(classes)
+    for check in INTEGRITY_CHECKS {
+        let ids = collect_strings(conn, check.ids_sql)?;
+        if !ids.is_empty() {
+            issues.push(integrity_issue(check.code, check.description, ids, check.repair_sql.is_some()));
+        }
     }
 
-    pub fn create_class(&self, req: CreateClassRequest) -> Result<Class, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let id = Uuid::new_v4().to_string();
-        let created_at = Utc::now();
-        let created_at_str = created_at.to_rfc3339();
+    Ok(IntegrityReport {
+        healthy: issues.is_empty(),
+        issues,
+        checked_at: Utc::now(),
+    })
+}
 
-        conn.execute(
-            "INSERT INTO classes (id, name, description, student_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![id, req.name, req.description, 0, created_at_str],
-        )?;
+fn query_schema_info(conn: &Connection) -> SqliteResult<SchemaInfo> {
+    let version = migrations::current_version(conn)?;
 
-        Ok(Class {
-            id,
-            name: req.name,
-            description: req.description,
-            student_count: 0,
-            created_at,
+    let mut stmt = conn.prepare("SELECT version, name, applied_at FROM schema_migrations ORDER BY version ASC")?;
+    let migration_iter = stmt.query_map([], |row| {
+        let applied_at_str: String = row.get(2)?;
+        let applied_at = DateTime::parse_from_rfc3339(&applied_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            applied_at,
         })
+    })?;
+
+    let mut applied_migrations = Vec::new();
+    for migration in migration_iter {
+        applied_migrations.push(migration?);
     }
 
-    pub fn update_class(&self, id: &str, req: UpdateClassRequest) -> Result<Class, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+    Ok(SchemaInfo {
+        version,
+        latest_version: migrations::latest_version(),
+        migrations: applied_migrations,
+    })
+}
 
-        // Build dynamic update query
-        if let Some(name) = &req.name {
-            conn.execute(
-                "UPDATE classes SET name = ? WHERE id = ?",
-                params![name, id],
-            )?;
-        }
+const CLASS_BUNDLE_FORMAT: &str = "pointhub-class-bundle";
+const CLASS_BUNDLE_VERSION: i32 = 1;
+
+// Import modes accepted by import_class_bundle
+const BUNDLE_IMPORT_NEW: &str = "new";
+const BUNDLE_IMPORT_MERGE: &str = "merge";
+
+fn collect_rows<T, P: rusqlite::Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+    f: impl FnMut(&rusqlite::Row) -> SqliteResult<T>,
+) -> SqliteResult<Vec<T>> {
+    let mut stmt = conn.prepare(sql)?;
+    let row_iter = stmt.query_map(params, f)?;
+
+    let mut rows = Vec::new();
+    for row in row_iter {
+        rows.push(row?);
+    }
+    Ok(rows)
+}
 
-        if let Some(description) = &req.description {
-            conn.execute(
-                "UPDATE classes SET description = ? WHERE id = ?",
-                params![description, id],
-            )?;
-        }
+fn validate_class_bundle(bundle: &ClassBundle) -> Result<(), AppError> {
+    if bundle.format != CLASS_BUNDLE_FORMAT {
+        return Err(AppError::invalid_input("不是有效的班级数据包"));
+    }
+    if bundle.version < 1 || bundle.version > CLASS_BUNDLE_VERSION {
+        return Err(AppError::invalid_input(format!("不支持的班级数据包版本: {}，请升级程序", bundle.version)));
+    }
+    if bundle.class.name.trim().is_empty() {
+        return Err(AppError::invalid_input("班级数据包缺少班级名称"));
+    }
 
-        if req.name.is_none() && req.description.is_none() {
-            return Err("No fields to update".into());
+    // The bundle is hand-editable JSON, so rows are checked against the same rules the
+    // commands that normally write them enforce
+    for student in bundle.students.iter().chain(&bundle.archived_students) {
+        if student.name.trim().is_empty() || student.student_number.trim().is_empty() {
+            return Err(AppError::invalid_input(format!("班级数据包中的学生 {} 缺少姓名或学号", student.id)));
+        }
+    }
+    for product in bundle.products.iter().chain(&bundle.archived_products) {
+        if product.points < 0 || product.stock < 0 {
+            return Err(AppError::invalid_input(format!("班级数据包中的商品 {} 积分或库存为负数", product.id)));
         }
+    }
+    for record in &bundle.purchase_records {
+        if record.quantity <= 0 {
+            return Err(AppError::invalid_input(format!("班级数据包中的购买记录 {} 数量必须大于0", record.id)));
+        }
+        if record.refunded_quantity < 0 || record.refunded_quantity > record.quantity {
+            return Err(AppError::invalid_input(format!("班级数据包中的购买记录 {} 退款数量超出购买数量", record.id)));
+        }
+        if record.points < 0 {
+            return Err(AppError::invalid_input(format!("班级数据包中的购买记录 {} 积分为负数", record.id)));
+        }
+        let status = normalize_shipping_status(&record.shipping_status);
+        if !SHIPPING_STATUSES.contains(&status) && status != SHIPPING_STATUS_REFUNDED {
+            return Err(AppError::invalid_input(format!(
+                "班级数据包中的购买记录 {} 发货状态无效: {}",
+                record.id, record.shipping_status
+            )));
+        }
+    }
+    for reason in &bundle.point_reasons {
+        if reason.label.trim().is_empty() {
+            return Err(AppError::invalid_input(format!("班级数据包中的积分原因 {} 缺少名称", reason.id)));
+        }
+        resolve_point_reason_category(Some(&reason.category), reason.default_delta)
+            .map_err(|e| AppError::invalid_input(format!("班级数据包中的积分原因 {}：{}", reason.id, e.message)))?;
+    }
+    if let Some(wheel_config) = &bundle.wheel_config {
+        if wheel_config.spin_cost <= 0 {
+            return Err(AppError::invalid_input("班级数据包中的抽奖消耗积分必须大于0"));
+        }
+        // Slots may only point at live products; an archived one would be spun but never granted
+        if let Some(product_id) = wheel_config.product_ids.iter().find(|id| !bundle.products.iter().any(|p| &p.id == *id)) {
+            return Err(AppError::invalid_input(format!("班级数据包中的转盘奖品 {} 不在商品列表中", product_id)));
+        }
+    }
+    Ok(())
+}
 
-        // Update class_name in students table if name changed
-        if let Some(new_name) = &req.name {
-            conn.execute(
-                "UPDATE students SET class_name = ? WHERE class_id = ?",
-                params![new_name, id],
+/// Finds an existing student in the class to merge a bundled one into: by student number
+/// when it has one, otherwise by name.
+fn find_merge_student(conn: &Connection, class_id: &str, student: &Student) -> SqliteResult<Option<String>> {
+    if !student.student_number.trim().is_empty() {
+        return conn
+            .query_row(
+                "SELECT id FROM students WHERE class_id = ? AND student_number = ? ORDER BY deleted_at IS NOT NULL LIMIT 1",
+                params![class_id, student.student_number],
+                |row| row.get(0),
+            )
+            .optional();
+    }
+
+    conn.query_row(
+        "SELECT id FROM students WHERE class_id = ? AND name = ? ORDER BY deleted_at IS NOT NULL LIMIT 1",
+        params![class_id, student.name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// Actions in a roster import plan
+const IMPORT_ACTION_CREATE: &str = "create";
+const IMPORT_ACTION_UPDATE: &str = "update";
+const IMPORT_ACTION_UNCHANGED: &str = "unchanged";
+const IMPORT_ACTION_CONFLICT: &str = "conflict";
+
+fn live_class_name(conn: &Connection, class_id: &str) -> Result<String, AppError> {
+    conn.query_row(
+        "SELECT name FROM classes WHERE id = ? AND deleted_at IS NULL",
+        [class_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("班级不存在"))
+}
+
+/// Matches a roster row to the class by student number and works out what importing it
+/// would change. A blank 积分 cell leaves an existing student's points alone.
+fn plan_import_row(conn: &Connection, class_id: &str, row: StudentImportRow) -> SqliteResult<StudentImportPlanRow> {
+    let matches = collect_rows(
+        conn,
+        "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students
+         WHERE class_id = ? AND student_number = ? AND deleted_at IS NULL",
+        params![class_id, row.student_number],
+        row_to_student,
+    )?;
+
+    let mut plan = StudentImportPlanRow {
+        row: row.row,
+        action: IMPORT_ACTION_CREATE.to_string(),
+        student_number: row.student_number,
+        name: row.name,
+        points: row.points,
+        student_id: None,
+        changes: Vec::new(),
+        message: None,
+    };
+
+    match matches.as_slice() {
+        [] => {
+            let in_trash: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM students WHERE class_id = ? AND student_number = ? AND deleted_at IS NOT NULL)",
+                params![class_id, plan.student_number],
+                |row| row.get(0),
             )?;
+            if in_trash {
+                plan.action = IMPORT_ACTION_CONFLICT.to_string();
+                plan.message = Some("该学号的学生在回收站中，请先恢复".to_string());
+            }
+        }
+        [existing] => {
+            plan.student_id = Some(existing.id.clone());
+            if existing.name != plan.name {
+                plan.changes.push(StudentFieldChange {
+                    field: student_import::FIELD_NAME.to_string(),
+                    before: existing.name.clone(),
+                    after: plan.name.clone(),
+                });
+            }
+            if let Some(points) = plan.points.filter(|points| *points != existing.points) {
+                plan.changes.push(StudentFieldChange {
+                    field: student_import::FIELD_POINTS.to_string(),
+                    before: existing.points.to_string(),
+                    after: points.to_string(),
+                });
+            }
+            plan.action = if plan.changes.is_empty() { IMPORT_ACTION_UNCHANGED } else { IMPORT_ACTION_UPDATE }.to_string();
+        }
+        _ => {
+            plan.action = IMPORT_ACTION_CONFLICT.to_string();
+            plan.message = Some(format!("班级中有 {} 名学生使用该学号", matches.len()));
         }
+    }
 
-        // Get updated class
-        let mut stmt = conn.prepare("SELECT id, name, description, student_count, created_at FROM classes WHERE id = ?")?;
-        let class = stmt.query_row([id], |row| {
-            let created_at_str: String = row.get(4)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+    Ok(plan)
+}
 
-            Ok(Class {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                student_count: row.get(3)?,
-                created_at,
-            })
+fn insert_imported_student(conn: &Connection, class_id: &str, class_name: &str, student_number: &str, name: &str, points: i32) -> SqliteResult<Student> {
+    let student = Student {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        student_number: student_number.to_string(),
+        points,
+        class_id: class_id.to_string(),
+        class_name: class_name.to_string(),
+        created_at: Utc::now(),
+    };
+
+    conn.execute(
+        "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![student.id, student.name, student.student_number, student.points, student.class_id, student.class_name, student.created_at.to_rfc3339()],
+    )?;
+
+    if student.points != 0 {
+        record_point_transaction(conn, PointChange {
+            student_id: &student.id,
+            class_id,
+            delta: student.points,
+            balance_after: student.points,
+            reason: Some("导入初始积分"),
+            source: POINT_SOURCE_IMPORT,
+            related_id: None,
+            reason_id: None,
         })?;
+    }
 
-        Ok(class)
+    write_audit_log(conn, AuditEntry {
+        entity_type: AUDIT_ENTITY_STUDENT,
+        entity_id: &student.id,
+        class_id: Some(class_id),
+        action: AUDIT_ACTION_IMPORT,
+        before: None,
+        after: to_json(&student),
+    })?;
+
+    Ok(student)
+}
+
+/// Checks the transition, stamps `shipped_at`/`received_at` and writes the audit entry.
+fn transition_shipping_status(conn: &Connection, before: &PurchaseRecord, status: &str, now: DateTime<Utc>) -> Result<PurchaseRecord, AppError> {
+    check_shipping_transition(&before.shipping_status, status)?;
+
+    let after = PurchaseRecord {
+        shipping_status: status.to_string(),
+        shipped_at: if status == SHIPPING_STATUS_SHIPPED { Some(now) } else { before.shipped_at },
+        received_at: if status == SHIPPING_STATUS_RECEIVED { Some(now) } else { before.received_at },
+        ..before.clone()
+    };
+
+    // Guarded by the old status so a record changed since it was read is never overwritten
+    let updated = conn.execute(
+        "UPDATE purchase_records SET shipping_status = ?1, shipped_at = ?2, received_at = ?3 WHERE id = ?4 AND shipping_status = ?5",
+        params![
+            after.shipping_status,
+            after.shipped_at.map(|time| time.to_rfc3339()),
+            after.received_at.map(|time| time.to_rfc3339()),
+            before.id,
+            before.shipping_status
+        ],
+    )?;
+    if updated == 0 {
+        return Err(AppError::conflict("购买记录状态已变化，请刷新后重试"));
     }
 
-    pub fn delete_class(&self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+    write_audit_log(conn, AuditEntry {
+        entity_type: AUDIT_ENTITY_PURCHASE_RECORD,
+        entity_id: &before.id,
+        class_id: Some(&before.class_id),
+        action: AUDIT_ACTION_UPDATE,
+        before: to_json(before),
+        after: to_json(&after),
+    })?;
 
-        // Delete students first
-        conn.execute("DELETE FROM students WHERE class_id = ?", [id])?;
-        conn.execute("DELETE FROM products WHERE class_id = ?", [id])?;
-        conn.execute("DELETE FROM purchase_records WHERE class_id = ?", [id])?;
-        conn.execute("DELETE FROM wheel_slots WHERE class_id = ?", [id])?;
-        conn.execute("DELETE FROM wheel_configs WHERE class_id = ?", [id])?;
+    Ok(after)
+}
 
-        // Delete class
-        conn.execute("DELETE FROM classes WHERE id = ?", [id])?;
+/// Sums the units still owed on `pending` and `ready` records (less anything refunded) — the
+/// same records the bulk-ship commands treat as outstanding — grouped by product and then
+/// student, ordered by class, product name and student number.
+fn query_pick_list(conn: &Connection, class_id: Option<&str>) -> Result<PickList, AppError> {
+    if let Some(class_id) = class_id {
+        live_class_name(conn, class_id)?;
+    }
 
-        Ok(())
+    struct PickRow {
+        class_id: String,
+        class_name: String,
+        product_id: String,
+        product_name: String,
+        student: PickListStudent,
     }
 
-    // Student CRUD operations
-    pub fn get_all_students(&self) -> Result<Vec<Student>, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students ORDER BY CAST(student_number AS INTEGER) ASC")?;
+    let rows = collect_rows(
+        conn,
+        "SELECT pr.class_id, c.name, pr.product_id, COALESCE(p.name, MAX(pr.product_name)),
+                pr.student_id, COALESCE(s.name, MAX(pr.student_name)), s.student_number,
+                SUM(pr.quantity - pr.refunded_quantity), COUNT(*)
+         FROM purchase_records pr
+         JOIN classes c ON c.id = pr.class_id AND c.deleted_at IS NULL
+         LEFT JOIN products p ON p.id = pr.product_id
+         LEFT JOIN students s ON s.id = pr.student_id
+         WHERE pr.shipping_status IN (?1, ?2)
+           AND pr.quantity > pr.refunded_quantity
+           AND (?3 IS NULL OR pr.class_id = ?3)
+         GROUP BY pr.class_id, pr.product_id, pr.student_id
+         ORDER BY c.name, pr.class_id, 4, pr.product_id, CAST(s.student_number AS INTEGER), 6",
+        params![SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY, class_id],
+        |row| {
+            Ok(PickRow {
+                class_id: row.get(0)?,
+                class_name: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                student: PickListStudent {
+                    student_id: row.get(4)?,
+                    student_name: row.get(5)?,
+                    student_number: row.get(6)?,
+                    quantity: row.get(7)?,
+                    record_count: row.get(8)?,
+                },
+            })
+        },
+    )?;
+
+    let mut pick_list = PickList {
+        class_id: class_id.map(str::to_string),
+        generated_at: Utc::now(),
+        total_quantity: 0,
+        record_count: 0,
+        products: Vec::new(),
+    };
+
+    for row in rows {
+        pick_list.total_quantity += row.student.quantity;
+        pick_list.record_count += row.student.record_count;
+
+        // Rows arrive sorted by product, so a product's students are contiguous
+        match pick_list.products.last_mut() {
+            Some(product) if product.product_id == row.product_id && product.class_id == row.class_id => {
+                product.total_quantity += row.student.quantity;
+                product.students.push(row.student);
+            }
+            _ => pick_list.products.push(PickListProduct {
+                product_id: row.product_id,
+                product_name: row.product_name,
+                class_id: row.class_id,
+                class_name: row.class_name,
+                total_quantity: row.student.quantity,
+                students: vec![row.student],
+            }),
+        }
+    }
 
-        let student_iter = stmt.query_map([], |row| {
-            let created_at_str: String = row.get(6)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+    Ok(pick_list)
+}
 
-            Ok(Student {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                student_number: row.get(2)?,
-                points: row.get(3)?,
-                class_id: row.get(4)?,
-                class_name: row.get(5)?,
-                created_at,
-            })
+/// Moves every record to `shipped` with one shared timestamp. The first record that cannot
+/// be shipped aborts the batch, naming the record.
+fn ship_records(conn: &Connection, records: &[PurchaseRecord]) -> Result<i32, AppError> {
+    let now = Utc::now();
+    for record in records {
+        transition_shipping_status(conn, record, SHIPPING_STATUS_SHIPPED, now).map_err(|e| {
+            AppError::new(e.code, format!("{}（{}）：{}", record.product_name, record.student_name, e.message))
+                .with_details(serde_json::json!({ "recordId": record.id }))
         })?;
+    }
+    Ok(records.len() as i32)
+}
 
-        let mut students = Vec::new();
-        for student in student_iter {
-            students.push(student?);
-        }
-
-        Ok(students)
+/// Points paid for the first `units` units of a record. Rounds down, so partial refunds
+/// never return more than was paid and refunding every unit returns exactly `points`.
+fn refund_share(record: &PurchaseRecord, units: i32) -> i32 {
+    if record.quantity <= 0 {
+        return 0;
     }
+    (i64::from(record.points) * i64::from(units) / i64::from(record.quantity)) as i32
+}
 
-    pub fn get_students_by_class(&self, class_id: &str) -> Result<Vec<Student>, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE class_id = ? ORDER BY CAST(student_number AS INTEGER) ASC")?;
-
-        let student_iter = stmt.query_map([class_id], |row| {
-            let created_at_str: String = row.get(6)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+/// Gives back the points and stock for `quantity` more units of a record, with a ledger
+/// row and an audit entry. Once every unit is refunded the record moves to `final_status`:
+/// `refunded`, or `cancelled` when the teacher cancels the order.
+fn refund_record(conn: &Connection, before: &PurchaseRecord, quantity: i32, reason: &str, final_status: &str) -> Result<RefundPurchaseResult, AppError> {
+    let (student_class_id, student_deleted_at): (String, Option<String>) = conn
+        .query_row(
+            "SELECT class_id, deleted_at FROM students WHERE id = ?",
+            [&before.student_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("学生不存在"))?;
+    if student_deleted_at.is_some() {
+        return Err(AppError::constraint_violation("学生已删除，请先从回收站恢复学生"));
+    }
 
-            Ok(Student {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                student_number: row.get(2)?,
-                points: row.get(3)?,
-                class_id: row.get(4)?,
-                class_name: row.get(5)?,
-                created_at,
-            })
+    let refunded_quantity = before.refunded_quantity + quantity;
+    let refunded_points = refund_share(before, refunded_quantity) - refund_share(before, before.refunded_quantity);
+    let shipping_status = if refunded_quantity == before.quantity {
+        final_status.to_string()
+    } else {
+        before.shipping_status.clone()
+    };
+
+    conn.execute(
+        "UPDATE purchase_records SET refunded_quantity = ?, shipping_status = ? WHERE id = ?",
+        params![refunded_quantity, shipping_status, before.id],
+    )?;
+
+    // A purged product has nothing left to restock
+    conn.execute(
+        "UPDATE products SET stock = stock + ? WHERE id = ?",
+        params![quantity, before.product_id],
+    )?;
+
+    conn.execute(
+        "UPDATE students SET points = points + ? WHERE id = ?",
+        params![refunded_points, before.student_id],
+    )?;
+    let student_points: i32 = conn.query_row(
+        "SELECT points FROM students WHERE id = ?",
+        [&before.student_id],
+        |row| row.get(0),
+    )?;
+
+    if refunded_points != 0 {
+        record_point_transaction(conn, PointChange {
+            student_id: &before.student_id,
+            class_id: &student_class_id,
+            delta: refunded_points,
+            balance_after: student_points,
+            reason: Some(reason),
+            source: POINT_SOURCE_REFUND,
+            related_id: Some(&before.id),
+            reason_id: None,
         })?;
+    }
 
-        let mut students = Vec::new();
-        for student in student_iter {
-            students.push(student?);
-        }
+    let record = PurchaseRecord {
+        refunded_quantity,
+        shipping_status,
+        ..before.clone()
+    };
+
+    write_audit_log(conn, AuditEntry {
+        entity_type: AUDIT_ENTITY_PURCHASE_RECORD,
+        entity_id: &before.id,
+        class_id: Some(&before.class_id),
+        action: AUDIT_ACTION_REFUND,
+        before: to_json(before),
+        after: to_json(&record),
+    })?;
+
+    Ok(RefundPurchaseResult {
+        record,
+        refunded_quantity: quantity,
+        refunded_points,
+        student_points,
+    })
+}
 
-        Ok(students)
-    }
+/// Converts a local `YYYY-MM-DD` date to the UTC instant of its midnight, `days_after` days later.
+fn local_date_start(date: &str, days_after: i64) -> Result<String, AppError> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| AppError::invalid_input(format!("无效的日期: {}", date)))?;
+    let midnight = (date + chrono::Duration::days(days_after)).and_hms_opt(0, 0, 0).ok_or_else(|| AppError::invalid_input("无效的日期"))?;
+    let start = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or_else(|| AppError::invalid_input("无效的日期"))?;
+    Ok(start.with_timezone(&Utc).to_rfc3339())
+}
 
-    pub fn create_student(&self, req: CreateStudentRequest) -> Result<Student, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let id = Uuid::new_v4().to_string();
-        let created_at = Utc::now();
-        let created_at_str = created_at.to_rfc3339();
+/// Pages are numbered from 1; rejecting zero sizes keeps the page count from dividing by zero.
+fn validate_page(page: i64, page_size: i64) -> Result<(), AppError> {
+    if page < 1 || page_size < 1 {
+        return Err(AppError::invalid_input("页码和每页条数必须大于0"));
+    }
+    Ok(())
+}
 
-        // Get class name
-        let class_name: String = conn.query_row(
-            "SELECT name FROM classes WHERE id = ?",
-            [&req.class_id],
-            |row| row.get(0),
-        )?;
+fn format_local_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string()
+}
 
-        conn.execute(
-            "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![id, req.name, req.student_number, req.points, req.class_id, class_name, created_at_str],
-        )?;
+// Keys stored in app_settings
+const SETTING_MIN_POINTS: &str = "min_points";
+const SETTING_BACKUP_RETENTION: &str = "backup_retention";
 
-        // Update class student count
-        conn.execute(
-            "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ?) WHERE id = ?",
-            params![req.class_id, req.class_id],
-        )?;
+const DEFAULT_BACKUP_RETENTION: i32 = 10;
 
-        Ok(Student {
-            id,
-            name: req.name,
-            student_number: req.student_number,
-            points: req.points,
-            class_id: req.class_id,
-            class_name,
-            created_at,
-        })
-    }
+fn get_setting_i32(conn: &Connection, key: &str, default: i32) -> SqliteResult<i32> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?", [key], |row| row.get(0))
+        .optional()?;
 
-    pub fn update_student(&self, id: &str, req: UpdateStudentRequest) -> Result<Student, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+    Ok(value.and_then(|value| value.parse().ok()).unwrap_or(default))
+}
 
-        // Get current student info
-        let current_student: Student = {
-            let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ?")?;
-            stmt.query_row([id], |row| {
-                let created_at_str: String = row.get(6)?;
-                let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now());
+fn set_setting(conn: &Connection, key: &str, value: &str) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
 
-                Ok(Student {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    student_number: row.get(2)?,
-                    points: row.get(3)?,
-                    class_id: row.get(4)?,
-                    class_name: row.get(5)?,
-                    created_at,
-                })
-            })?
-        };
+/// Applies `delta` to a student's balance in a single `points = points + ?` update.
+///
+/// Deductions that would take the balance below `min_points` are rejected; awards are
+/// always allowed, even for a student who is currently below the floor.
+fn apply_point_delta(
+    conn: &Connection,
+    student_id: &str,
+    delta: i32,
+    min_points: i32,
+    reason: Option<&str>,
+    reason_id: Option<&str>,
+    source: &str,
+) -> Result<PointAdjustmentResult, AppError> {
+    if delta == 0 {
+        return Err(AppError::invalid_input("积分变动值不能为0"));
+    }
 
-        // Update fields individually
-        let mut class_changed = false;
-        let mut has_updates = false;
+    let updated: Option<(i32, String, String)> = conn
+        .query_row(
+            "UPDATE students SET points = points + ?1
+             WHERE id = ?2 AND deleted_at IS NULL AND (?1 > 0 OR points + ?1 >= ?3)
+             RETURNING points, class_id, name",
+            params![delta, student_id, min_points],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
 
-        if let Some(name) = &req.name {
-            conn.execute(
-                "UPDATE students SET name = ? WHERE id = ?",
-                params![name, id],
-            )?;
-            has_updates = true;
-        }
+    let (balance, class_id, student_name) = match updated {
+        Some(updated) => updated,
+        None => {
+            let current: Option<(String, i32)> = conn
+                .query_row(
+                    "SELECT name, points FROM students WHERE id = ? AND deleted_at IS NULL",
+                    [student_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
 
-        if let Some(student_number) = &req.student_number {
-            conn.execute(
-                "UPDATE students SET student_number = ? WHERE id = ?",
-                params![student_number, id],
-            )?;
-            has_updates = true;
+            return match current {
+                Some((name, points)) => Err(AppError::insufficient_points(format!(
+                    "积分不足：{} 当前积分 {}，扣减 {} 后将低于最低积分 {}",
+                    name, points, -delta, min_points
+                ))),
+                None => Err(AppError::not_found("学生不存在")),
+            };
         }
+    };
+
+    let transaction = record_point_transaction(conn, PointChange {
+        student_id,
+        class_id: &class_id,
+        delta,
+        balance_after: balance,
+        reason,
+        source,
+        related_id: None,
+        reason_id,
+    })?;
+
+    write_audit_log(conn, AuditEntry {
+        entity_type: AUDIT_ENTITY_STUDENT,
+        entity_id: student_id,
+        class_id: Some(&class_id),
+        action: if source == POINT_SOURCE_UNDO { AUDIT_ACTION_UNDO_POINTS } else { AUDIT_ACTION_ADJUST_POINTS },
+        before: Some(serde_json::json!({ "points": balance - delta })),
+        after: Some(serde_json::json!({ "points": balance, "delta": delta, "reason": reason, "transactionId": transaction.id })),
+    })?;
+
+    Ok(PointAdjustmentResult {
+        student_id: student_id.to_string(),
+        student_name,
+        delta,
+        balance,
+        transaction_id: transaction.id,
+    })
+}
 
-        if let Some(points) = req.points {
-            conn.execute(
-                "UPDATE students SET points = ? WHERE id = ?",
-                params![points, id],
-            )?;
-            has_updates = true;
-        }
+/// Reverses a manual ledger entry by appending the opposite delta and linking the two rows.
+///
+/// Purchase and wheel entries are not undoable here, and an award cannot be taken back
+/// once the student has spent it and the reversal would push them under `min_points`.
+fn undo_point_transaction(conn: &Connection, transaction_id: &str, min_points: i32) -> Result<PointTransaction, AppError> {
+    let original: PointTransaction = conn
+        .query_row(
+            "SELECT id, student_id, class_id, delta, balance_after, reason, source, related_id, created_at, reason_id, reverts_id, reverted_by, operation_id
+             FROM point_transactions WHERE id = ?",
+            [transaction_id],
+            row_to_point_transaction,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("积分记录不存在"))?;
+
+    if original.reverted_by.is_some() {
+        return Err(AppError::conflict("该积分记录已撤销"));
+    }
+    if original.reverts_id.is_some() {
+        return Err(AppError::conflict("撤销记录不能再次撤销"));
+    }
+    if original.source != POINT_SOURCE_MANUAL {
+        return Err(AppError::conflict("兑换或抽奖产生的积分变动不能直接撤销"));
+    }
 
-        if let Some(class_id) = &req.class_id {
-            if class_id != &current_student.class_id {
-                class_changed = true;
-                has_updates = true;
+    let compensation = -original.delta;
+    if compensation < 0 {
+        let current_points: i32 = conn
+            .query_row("SELECT points FROM students WHERE id = ?", [&original.student_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::not_found("学生不存在"))?;
 
-                // Get new class name
-                let class_name: String = conn.query_row(
-                    "SELECT name FROM classes WHERE id = ?",
-                    [class_id],
-                    |row| row.get(0),
-                )?;
+        if current_points + compensation < min_points {
+            let spent_since: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM point_transactions
+                 WHERE student_id = ?1 AND source IN (?2, ?3) AND rowid > (SELECT rowid FROM point_transactions WHERE id = ?4)",
+                params![original.student_id, POINT_SOURCE_PURCHASE, POINT_SOURCE_WHEEL, original.id],
+                |row| row.get(0),
+            )?;
 
-                conn.execute(
-                    "UPDATE students SET class_id = ?, class_name = ? WHERE id = ?",
-                    params![class_id, class_name, id],
-                )?;
+            if spent_since > 0 {
+                return Err(AppError::insufficient_points(format!(
+                    "该积分已在之后的兑换中使用（当前积分 {}），撤销后将低于最低积分 {}",
+                    current_points, min_points
+                )));
             }
         }
+    }
 
-        if !has_updates {
-            return Ok(current_student);
-        }
+    let reason = match &original.reason {
+        Some(reason) => format!("撤销：{}", reason),
+        None => "撤销".to_string(),
+    };
+    let result = apply_point_delta(conn, &original.student_id, compensation, min_points, Some(&reason), original.reason_id.as_deref(), POINT_SOURCE_UNDO)?;
+
+    conn.execute(
+        "UPDATE point_transactions SET reverts_id = ?1 WHERE id = ?2",
+        params![original.id, result.transaction_id],
+    )?;
+    conn.execute(
+        "UPDATE point_transactions SET reverted_by = ?1 WHERE id = ?2",
+        params![result.transaction_id, original.id],
+    )?;
+
+    let reversal = conn.query_row(
+        "SELECT id, student_id, class_id, delta, balance_after, reason, source, related_id, created_at, reason_id, reverts_id, reverted_by, operation_id
+         FROM point_transactions WHERE id = ?",
+        [&result.transaction_id],
+        row_to_point_transaction,
+    )?;
+
+    Ok(reversal)
+}
 
-        // Update student counts if class changed
-        if class_changed {
-            if let Some(new_class_id) = &req.class_id {
-                // Update old class student count
-                conn.execute(
-                    "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ?) WHERE id = ?",
-                    params![current_student.class_id, current_student.class_id],
-                )?;
+fn row_to_point_reason(row: &rusqlite::Row) -> SqliteResult<PointReason> {
+    let created_at_str: String = row.get(6)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(PointReason {
+        id: row.get(0)?,
+        class_id: row.get(1)?,
+        label: row.get(2)?,
+        default_delta: row.get(3)?,
+        category: row.get(4)?,
+        sort_order: row.get(5)?,
+        created_at,
+    })
+}
 
-                // Update new class student count
-                conn.execute(
-                    "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ?) WHERE id = ?",
-                    params![new_class_id, new_class_id],
-                )?;
+/// Looks up the label of a catalog reason so it can stand in for a free-text reason.
+/// Reasons belong to a class and may only be used for that class's students; students
+/// that do not exist are left for `apply_point_delta` to report.
+fn point_reason_label(conn: &Connection, reason_id: Option<&str>, student_ids: &[&str]) -> Result<Option<String>, AppError> {
+    match reason_id {
+        Some(reason_id) => {
+            let (label, class_id): (String, String) = conn
+                .query_row("SELECT label, class_id FROM point_reasons WHERE id = ?", [reason_id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()?
+                .ok_or_else(|| AppError::not_found("积分原因不存在"))?;
+
+            for student_id in student_ids {
+                let student_class_id: Option<String> = conn
+                    .query_row("SELECT class_id FROM students WHERE id = ? AND deleted_at IS NULL", [student_id], |row| row.get(0))
+                    .optional()?;
+                if student_class_id.is_some_and(|student_class_id| student_class_id != class_id) {
+                    return Err(AppError::invalid_input("积分原因不属于学生所在的班级"));
+                }
             }
+            Ok(Some(label))
         }
+        None => Ok(None),
+    }
+}
 
-        // Get updated student
-        let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ?")?;
-        let student = stmt.query_row([id], |row| {
-            let created_at_str: String = row.get(6)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
-
-            Ok(Student {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                student_number: row.get(2)?,
-                points: row.get(3)?,
-                class_id: row.get(4)?,
-                class_name: row.get(5)?,
-                created_at,
-            })
-        })?;
+/// Reasons are either "positive" (awards) or "negative" (deductions); the default delta must agree.
+fn resolve_point_reason_category(category: Option<&str>, default_delta: i32) -> Result<String, AppError> {
+    if default_delta == 0 {
+        return Err(AppError::invalid_input("默认分值不能为0"));
+    }
 
-        Ok(student)
+    let expected = if default_delta > 0 { "positive" } else { "negative" };
+    match category {
+        None => Ok(expected.to_string()),
+        Some(category) if category == expected => Ok(category.to_string()),
+        Some("positive") | Some("negative") => Err(AppError::invalid_input("原因类别与默认分值的正负不一致")),
+        Some(category) => Err(AppError::invalid_input(format!("无效的原因类别: {}", category))),
     }
+}
 
-    pub fn delete_student(&self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+const DB_FILE_NAME: &str = "pointhub.db";
 
-        // Get class_id before deleting
-        let class_id: String = conn.query_row(
-            "SELECT class_id FROM students WHERE id = ?",
-            [id],
-            |row| row.get(0),
-        )?;
+// Drive letters older versions probed for a data directory
+const LEGACY_DATA_DIRS: [&str; 3] = ["D:\\PointHub", "E:\\PointHub", "F:\\PointHub"];
 
-        // Delete student
-        conn.execute("DELETE FROM students WHERE id = ?", [id])?;
+/// Tables compared row for row when verifying a moved database.
+const MOVE_VERIFY_TABLES: [&str; 6] = ["classes", "students", "products", "purchase_records", "point_transactions", "audit_logs"];
 
-        // Update class student count
-        conn.execute(
-            "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ?) WHERE id = ?",
-            params![class_id, class_id],
-        )?;
+fn detect_database(dir: &Path) -> Option<DetectedDatabase> {
+    let db_path = dir.join(DB_FILE_NAME);
+    let metadata = std::fs::metadata(&db_path).ok().filter(|metadata| metadata.is_file())?;
 
-        Ok(())
-    }
+    let schema_version = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .ok()
+        .and_then(|conn| migrations::current_version(&conn).ok());
 
-    // Product CRUD operations
-    pub fn get_products_by_class(&self, class_id: &str) -> Result<Vec<Product>, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, points, stock, class_id, created_at FROM products WHERE class_id = ? ORDER BY created_at DESC")?;
+    Some(DetectedDatabase {
+        data_dir: dir.to_string_lossy().to_string(),
+        db_path: db_path.to_string_lossy().to_string(),
+        size_bytes: metadata.len(),
+        schema_version,
+        modified_at: metadata.modified().ok().map(DateTime::<Utc>::from),
+    })
+}
 
-        let product_iter = stmt.query_map([class_id], |row| {
-            let created_at_str: String = row.get(5)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+/// Copies the live database to `target_path` and checks the copy is intact and complete
+/// before anything is switched over to it.
+fn copy_and_verify_database(conn: &Connection, target_path: &Path) -> Result<(), AppError> {
+    conn.backup(DatabaseName::Main, target_path, None)
+        .map_err(|e| AppError::io(format!("复制数据库失败: {}", e)))?;
 
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                points: row.get(2)?,
-                stock: row.get(3)?,
-                class_id: row.get(4)?,
-                created_at,
-            })
-        })?;
+    let copy = Connection::open_with_flags(target_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
 
-        let mut products = Vec::new();
-        for product in product_iter {
-            products.push(product?);
-        }
+    let integrity: String = copy.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(AppError::io(format!("数据库副本校验失败: {}", integrity)));
+    }
 
-        Ok(products)
+    if migrations::current_version(&copy)? != migrations::current_version(conn)? {
+        return Err(AppError::io("数据库副本校验失败: 版本不一致"));
     }
 
-    pub fn create_product(&self, req: CreateProductRequest) -> Result<Product, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        let id = Uuid::new_v4().to_string();
-        let created_at = Utc::now();
-        let created_at_str = created_at.to_rfc3339();
+    for table in MOVE_VERIFY_TABLES {
+        let sql = format!("SELECT COUNT(*) FROM {}", table);
+        let expected: i64 = conn.query_row(&sql, [], |row| row.get(0))?;
+        let actual: i64 = copy.query_row(&sql, [], |row| row.get(0))?;
+        if expected != actual {
+            return Err(AppError::io(format!("数据库副本校验失败: {} 记录数不一致", table)));
+        }
+    }
 
-        conn.execute(
-            "INSERT INTO products (id, name, points, stock, class_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![id, req.name, req.points, req.stock, req.class_id, created_at_str],
-        )?;
+    Ok(())
+}
 
-        Ok(Product {
-            id,
-            name: req.name,
-            points: req.points,
-            stock: req.stock,
-            class_id: req.class_id,
-            created_at,
-        })
+/// Copies the backups under `old_dir` next to `new_db_path`, recording each file it writes
+/// so a failed move can remove them again.
+fn copy_backups(old_dir: &Path, new_db_path: &Path, copied: &mut Vec<PathBuf>) -> Result<(), AppError> {
+    let old_backup_dir = old_dir.join("backups");
+    if !old_backup_dir.is_dir() {
+        return Ok(());
     }
 
-    pub fn update_product(&self, id: &str, req: UpdateProductRequest) -> Result<Product, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+    let new_backup_dir = backup::backup_dir(new_db_path)?;
+    for entry in std::fs::read_dir(&old_backup_dir)? {
+        let entry = entry?;
+        let destination = new_backup_dir.join(entry.file_name());
+        if entry.path().is_file() && !destination.exists() {
+            std::fs::copy(entry.path(), &destination)?;
+            copied.push(destination.clone());
+            // Backups are listed and rotated by modification time, which a copy resets
+            let modified = entry.metadata()?.modified()?;
+            std::fs::File::options().write(true).open(&destination)?.set_modified(modified)?;
+        }
+    }
+    Ok(())
+}
 
-        let mut has_updates = false;
+pub struct Database {
+    pub conn: Mutex<Connection>,
+    db_path: RwLock<PathBuf>,
+}
 
-        if let Some(name) = &req.name {
-            conn.execute(
-                "UPDATE products SET name = ? WHERE id = ?",
-                params![name, id],
-            )?;
-            has_updates = true;
-        }
+impl Database {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let db_dir = Self::resolve_data_dir(app_handle)?;
+        std::fs::create_dir_all(&db_dir)?;
 
-        if let Some(points) = req.points {
-            conn.execute(
-                "UPDATE products SET points = ? WHERE id = ?",
-                params![points, id],
-            )?;
-            has_updates = true;
-        }
+        let database = Self::open(&db_dir.join(DB_FILE_NAME))?;
+        database.init_sample_data()?;
 
-        if let Some(stock) = req.stock {
-            conn.execute(
-                "UPDATE products SET stock = ? WHERE id = ?",
-                params![stock, id],
-            )?;
-            has_updates = true;
+        // A failed startup backup must not keep the teacher out of their data, so it is
+        // recorded in the audit log instead. If even that write fails there is nowhere
+        // left to report it.
+        {
+            let conn = database.conn.lock().unwrap();
+            if let Err(e) = database.auto_backup(&conn, backup::BACKUP_KIND_STARTUP) {
+                let _ = write_audit_log(&conn, AuditEntry {
+                    entity_type: AUDIT_ENTITY_DATABASE,
+                    entity_id: backup::BACKUP_KIND_STARTUP,
+                    class_id: None,
+                    action: AUDIT_ACTION_BACKUP_FAILED,
+                    before: None,
+                    after: Some(serde_json::json!({ "code": e.code, "message": e.message })),
+                });
+            }
         }
 
-        if !has_updates {
-            return Err("No fields to update".into());
+        Ok(database)
+    }
+
+    /// Opens the database file and brings its schema up to date.
+    pub fn open(db_path: &Path) -> Result<Self, AppError> {
+        let mut conn = Connection::open(db_path)?;
+        migrations::run_migrations(&mut conn, db_path)?;
+
+        Ok(Database {
+            conn: Mutex::new(conn),
+            db_path: RwLock::new(db_path.to_path_buf()),
+        })
+    }
+
+    /// The data directory comes from settings.json. Before one is saved, a database left
+    /// in a legacy drive-letter location is adopted; otherwise the app data dir is used.
+    fn resolve_data_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+        let config_dir = app_handle.path().app_config_dir()?;
+        let mut config = AppConfig::load(&config_dir)?;
+
+        if let Some(data_dir) = &config.data_dir {
+            return Ok(data_dir.clone());
         }
 
-        // Get updated product
-        let mut stmt = conn.prepare("SELECT id, name, points, stock, class_id, created_at FROM products WHERE id = ?")?;
-        let product = stmt.query_row([id], |row| {
-            let created_at_str: String = row.get(5)?;
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
+        let data_dir = LEGACY_DATA_DIRS
+            .iter()
+            .map(PathBuf::from)
+            .find(|dir| dir.join(DB_FILE_NAME).is_file())
+            .map_or_else(|| app_handle.path().app_data_dir(), Ok)?;
 
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                points: row.get(2)?,
-                stock: row.get(3)?,
-                class_id: row.get(4)?,
-                created_at,
-            })
-        })?;
+        config.data_dir = Some(data_dir.clone());
+        config.save(&config_dir)?;
 
-        Ok(product)
+        Ok(data_dir)
     }
 
-    pub fn delete_product(&self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM products WHERE id = ?", [id])?;
-        Ok(())
+    fn db_path(&self) -> PathBuf {
+        self.db_path.read().unwrap().clone()
     }
 
-    // Purchase records methods
-    pub fn create_purchase_record(&self, req: CreatePurchaseRequest) -> Result<PurchaseRecord, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
+    /// Takes an automatic backup and rotates old ones out according to the retention setting.
+    fn auto_backup(&self, conn: &Connection, kind: &str) -> Result<BackupInfo, AppError> {
+        let info = backup::create_backup(conn, &self.db_path(), kind)?;
+        self.prune_backups(conn)?;
+        Ok(info)
+    }
 
-        // Start transaction
-        conn.execute("BEGIN TRANSACTION", [])?;
+    fn prune_backups(&self, conn: &Connection) -> Result<(), AppError> {
+        let retention = get_setting_i32(conn, SETTING_BACKUP_RETENTION, DEFAULT_BACKUP_RETENTION)?;
+        backup::prune_automatic_backups(&self.db_path(), retention.max(1) as usize)
+    }
 
-        // Get product and student info
-        let product: Product = conn.query_row(
-            "SELECT id, name, points, stock, class_id, created_at FROM products WHERE id = ?",
-            [&req.product_id],
-            |row| {
-                Ok(Product {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    points: row.get(2)?,
-                    stock: row.get(3)?,
-                    class_id: row.get(4)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?).map_err(|_| rusqlite::Error::InvalidColumnType(5, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
-                })
-            },
-        )?;
-
-        let student: Student = conn.query_row(
-            "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ?",
-            [&req.student_id],
-            |row| {
-                Ok(Student {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    student_number: row.get(2)?,
-                    points: row.get(3)?,
-                    class_id: row.get(4)?,
-                    class_name: row.get(5)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?).map_err(|_| rusqlite::Error::InvalidColumnType(6, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
-                })
-            },
-        )?;
-
-        // Check if product has enough stock
-        if product.stock < req.quantity {
-            conn.execute("ROLLBACK", [])?;
-            return Err("库存不足".into());
-        }
-
-        let total_points = product.points * req.quantity;
+    fn init_sample_data(&self) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
 
-        // Check if student has enough points
-        if student.points < total_points {
-            conn.execute("ROLLBACK", [])?;
-            return Err("积分不足".into());
+        // Check if data already exists
+        let count: i32 = tx.query_row("SELECT COUNT(*) FROM classes", [], |row| row.get(0))?;
+        if count > 0 {
+            return Ok(());
         }
 
-        // Create purchase record
-        let id = Uuid::new_v4().to_string();
-        let created_at = Utc::now();
+        // Insert sample class
+        let class_id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
 
-        conn.execute(
-            "INSERT INTO purchase_records (id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        tx.execute(
+            "INSERT INTO classes (id, name, description, student_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
             params![
-                &id,
-                &product.id,
-                &product.name,
-                &total_points,
-                &student.id,
-                &student.name,
-                &req.quantity,
-                &product.class_id,
-                &created_at.to_rfc3339(),
-                "pending",
-                "购买"
+                class_id,
+                "计算机科学与技术2021级1班",
+                "计算机科学与技术专业",
+                1,
+                now
             ],
         )?;
 
-        // Update student points
-        conn.execute(
-            "UPDATE students SET points = points - ? WHERE id = ?",
-            params![&total_points, &student.id],
+        // Insert sample student
+        let student_id = Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                student_id,
+                "张三",
+                "2021001",
+                85,
+                class_id,
+                "计算机科学与技术2021级1班",
+                now
+            ],
         )?;
 
-        // Update product stock
-        conn.execute(
-            "UPDATE products SET stock = stock - ? WHERE id = ?",
-            params![&req.quantity, &product.id],
-        )?;
+        // The sample balance gets its opening ledger row like any other student's
+        record_point_transaction(&tx, PointChange {
+            student_id: &student_id,
+            class_id: &class_id,
+            delta: 85,
+            balance_after: 85,
+            reason: Some("初始积分"),
+            source: POINT_SOURCE_OPENING,
+            related_id: None,
+            reason_id: None,
+        })?;
 
-        // Commit transaction
-        conn.execute("COMMIT", [])?;
+        tx.commit()
+    }
 
-        Ok(PurchaseRecord {
-            id,
-            product_id: product.id,
-            product_name: product.name,
-            points: total_points,
-            student_id: student.id,
-            student_name: student.name,
-            quantity: req.quantity,
-            class_id: product.class_id,
-            created_at,
-            shipping_status: "pending".to_string(),
-            source: "购买".to_string(),
-        })
+    pub fn get_schema_info(&self) -> Result<SchemaInfo, AppError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(query_schema_info(&conn)?)
     }
 
-    pub fn get_purchase_records_by_class(&self, class_id: &str) -> Result<Vec<PurchaseRecord>, Box<dyn std::error::Error>> {
+    // Class CRUD operations
+    pub fn get_all_classes(&self) -> Result<Vec<Class>, AppError> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source FROM purchase_records WHERE class_id = ? ORDER BY created_at DESC")?;
+        let mut stmt = conn.prepare("SELECT id, name, description, student_count, created_at FROM classes WHERE deleted_at IS NULL ORDER BY created_at DESC")?;
+
+        let class_iter = stmt.query_map([], |row| {
+            let created_at_str: String = row.get(4)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
 
-        let records = stmt.query_map([class_id], |row| {
-            Ok(PurchaseRecord {
+            Ok(Class {
                 id: row.get(0)?,
-                product_id: row.get(1)?,
-                product_name: row.get(2)?,
-                points: row.get(3)?,
-                student_id: row.get(4)?,
-                student_name: row.get(5)?,
-                quantity: row.get(6)?,
-                class_id: row.get(7)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).map_err(|_| rusqlite::Error::InvalidColumnType(8, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
-                shipping_status: row.get(9)?,
-                source: row.get(10)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                student_count: row.get(3)?,
+                created_at,
             })
         })?;
 
-        let mut result = Vec::new();
-        for record in records {
-            result.push(record?);
+        let mut classes = Vec::new();
+        for class in class_iter {
+            classes.push(class?);
         }
 
-        Ok(result)
+        Ok(classes)
     }
 
-    pub fn get_purchase_records_paginated(&self, class_id: &str, page: i64, page_size: i64, source: Option<&str>) -> Result<PaginatedPurchaseRecords, Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-
-        let source_filter = source.filter(|value| !value.trim().is_empty() && *value != "all");
-        let source_value = source_filter.unwrap_or("");
+    pub fn create_class(&self, req: CreateClassRequest) -> Result<Class, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        let created_at_str = created_at.to_rfc3339();
 
-        // 获取总记录数
-        let total: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM purchase_records WHERE class_id = ?1 AND (?2 = '' OR source = ?2)",
-            params![class_id, source_value],
-            |row| row.get(0),
+        tx.execute(
+            "INSERT INTO classes (id, name, description, student_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, req.name, req.description, 0, created_at_str],
         )?;
 
-        // 计算总页数
-        let total_pages = (total + page_size - 1) / page_size;
+        let class = Class {
+            id,
+            name: req.name,
+            description: req.description,
+            student_count: 0,
+            created_at,
+        };
 
-        // 计算偏移量
-        let offset = (page - 1) * page_size;
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_CLASS,
+            entity_id: &class.id,
+            class_id: Some(&class.id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&class),
+        })?;
 
-        // 分页查询数据
-        let mut stmt = conn.prepare(
-            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source
-             FROM purchase_records
-             WHERE class_id = ?1 AND (?2 = '' OR source = ?2)
-             ORDER BY created_at DESC
-             LIMIT ?3 OFFSET ?4"
-        )?;
+        tx.commit()?;
+        Ok(class)
+    }
 
-        let records = stmt.query_map(params![class_id, source_value, page_size, offset], |row| {
-            Ok(PurchaseRecord {
-                id: row.get(0)?,
-                product_id: row.get(1)?,
-                product_name: row.get(2)?,
-                points: row.get(3)?,
-                student_id: row.get(4)?,
-                student_name: row.get(5)?,
-                quantity: row.get(6)?,
-                class_id: row.get(7)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).map_err(|_| rusqlite::Error::InvalidColumnType(8, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
-                shipping_status: row.get(9)?,
-                source: row.get(10)?,
-            })
-        })?;
+    pub fn update_class(&self, id: &str, req: UpdateClassRequest) -> Result<Class, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
 
-        let mut result = Vec::new();
-        for record in records {
-            result.push(record?);
+        if req.name.is_none() && req.description.is_none() {
+            return Err(AppError::invalid_input("No fields to update"));
         }
 
-        Ok(PaginatedPurchaseRecords {
-            records: result,
-            total,
-            total_pages,
-            current_page: page,
-            page_size,
-        })
+        let before = live_class(&tx, id)?;
+
+        // Build dynamic update query
+        if let Some(name) = &req.name {
+            tx.execute(
+                "UPDATE classes SET name = ? WHERE id = ?",
+                params![name, id],
+            )?;
+        }
+
+        if let Some(description) = &req.description {
+            tx.execute(
+                "UPDATE classes SET description = ? WHERE id = ?",
+                params![description, id],
+            )?;
+        }
+
+        // Update class_name in students table if name changed
+        if let Some(new_name) = &req.name {
+            tx.execute(
+                "UPDATE students SET class_name = ? WHERE class_id = ?",
+                params![new_name, id],
+            )?;
+        }
+
+        // Get updated class
+        let class = query_class(&tx, id)?.ok_or_else(|| AppError::not_found("班级不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_CLASS,
+            entity_id: id,
+            class_id: Some(id),
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&before),
+            after: to_json(&class),
+        })?;
+
+        tx.commit()?;
+        Ok(class)
     }
 
-    pub fn get_wheel_config(&self, class_id: &str) -> Result<WheelConfig, Box<dyn std::error::Error>> {
+    /// Counts what `delete_class` would take with it, for the confirm dialog.
+    pub fn preview_class_deletion(&self, id: &str) -> Result<ClassDeletionPreview, AppError> {
         let conn = self.conn.lock().unwrap();
-        let now_rfc3339 = Utc::now().to_rfc3339();
-        let class_exists: Option<String> = conn
-            .query_row("SELECT id FROM classes WHERE id = ?", [class_id], |row| row.get(0))
-            .optional()?;
-        if class_exists.is_none() {
-            return Err("班级不存在".into());
-        }
 
-        let existing: Option<(i32, String, String)> = conn
+        let class_name: String = conn
             .query_row(
-                "SELECT spin_cost, created_at, updated_at FROM wheel_configs WHERE class_id = ?",
-                [class_id],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                "SELECT name FROM classes WHERE id = ? AND deleted_at IS NULL",
+                [id],
+                |row| row.get(0),
             )
-            .optional()?;
+            .optional()?
+            .ok_or_else(|| AppError::not_found("班级不存在"))?;
 
-        if existing.is_none() {
-            conn.execute(
-                "INSERT INTO wheel_configs (class_id, spin_cost, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-                params![class_id, 10, now_rfc3339, now_rfc3339],
-            )?;
-        }
+        let count = |sql: &str| -> SqliteResult<i32> { conn.query_row(sql, [id], |row| row.get(0)) };
 
-        let (spin_cost, created_at_raw, updated_at_raw): (i32, String, String) = conn.query_row(
-            "SELECT spin_cost, created_at, updated_at FROM wheel_configs WHERE class_id = ?",
-            [class_id],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        // Students and products already in the recycle bin are purged together with the class
+        Ok(ClassDeletionPreview {
+            class_id: id.to_string(),
+            class_name,
+            students: count("SELECT COUNT(*) FROM students WHERE class_id = ? AND deleted_at IS NULL")?,
+            products: count("SELECT COUNT(*) FROM products WHERE class_id = ? AND deleted_at IS NULL")?,
+            trashed_students: count("SELECT COUNT(*) FROM students WHERE class_id = ? AND deleted_at IS NOT NULL")?,
+            trashed_products: count("SELECT COUNT(*) FROM products WHERE class_id = ? AND deleted_at IS NOT NULL")?,
+            purchase_records: count("SELECT COUNT(*) FROM purchase_records WHERE class_id = ?")?,
+            wheel_slots: count("SELECT COUNT(*) FROM wheel_slots WHERE class_id = ?")?,
+        })
+    }
+
+    /// Moves the class to the recycle bin. Its students and products are trashed with it
+    /// and share its `deleted_at`, which is how `restore_class` finds them again.
+    pub fn delete_class(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = query_class(&tx, id)?.ok_or_else(|| AppError::not_found("班级不存在"))?;
+        let deleted_at = Utc::now().to_rfc3339();
+
+        let rows = tx.execute(
+            "UPDATE classes SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            params![deleted_at, id],
         )?;
+        if rows == 0 {
+            return Err(AppError::not_found("班级不存在"));
+        }
 
-        let mut stmt = conn.prepare(
-            "SELECT
-                ws.id,
-                ws.class_id,
-                ws.product_id,
-                p.name,
-                p.points,
-                p.stock,
-                ws.slot_index
-             FROM wheel_slots ws
-             JOIN products p ON p.id = ws.product_id
-             WHERE ws.class_id = ?
-             ORDER BY ws.slot_index ASC",
+        tx.execute(
+            "UPDATE students SET deleted_at = ?1 WHERE class_id = ?2 AND deleted_at IS NULL",
+            params![deleted_at, id],
+        )?;
+        tx.execute(
+            "UPDATE products SET deleted_at = ?1 WHERE class_id = ?2 AND deleted_at IS NULL",
+            params![deleted_at, id],
         )?;
 
-        let slot_rows = stmt.query_map([class_id], |row| {
-            Ok(WheelSlot {
-                id: row.get(0)?,
-                class_id: row.get(1)?,
-                product_id: row.get(2)?,
-                product_name: row.get(3)?,
-                product_points: row.get(4)?,
-                product_stock: row.get(5)?,
-                slot_index: row.get(6)?,
-            })
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_CLASS,
+            entity_id: id,
+            class_id: Some(id),
+            action: AUDIT_ACTION_DELETE,
+            before: to_json(&before),
+            after: None,
         })?;
 
-        let mut slots = Vec::new();
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Student CRUD operations
+    pub fn get_all_students(&self) -> Result<Vec<Student>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE deleted_at IS NULL ORDER BY CAST(student_number AS INTEGER) ASC")?;
+
+        let student_iter = stmt.query_map([], |row| {
+            let created_at_str: String = row.get(6)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+
+            Ok(Student {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                student_number: row.get(2)?,
+                points: row.get(3)?,
+                class_id: row.get(4)?,
+                class_name: row.get(5)?,
+                created_at,
+            })
+        })?;
+
+        let mut students = Vec::new();
+        for student in student_iter {
+            students.push(student?);
+        }
+
+        Ok(students)
+    }
+
+    pub fn get_students_by_class(&self, class_id: &str) -> Result<Vec<Student>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE class_id = ? AND deleted_at IS NULL ORDER BY CAST(student_number AS INTEGER) ASC")?;
+
+        let student_iter = stmt.query_map([class_id], |row| {
+            let created_at_str: String = row.get(6)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+
+            Ok(Student {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                student_number: row.get(2)?,
+                points: row.get(3)?,
+                class_id: row.get(4)?,
+                class_name: row.get(5)?,
+                created_at,
+            })
+        })?;
+
+        let mut students = Vec::new();
+        for student in student_iter {
+            students.push(student?);
+        }
+
+        Ok(students)
+    }
+
+    pub fn create_student(&self, req: CreateStudentRequest) -> Result<Student, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        let created_at_str = created_at.to_rfc3339();
+
+        // Get class name
+        let class_name: String = tx.query_row(
+            "SELECT name FROM classes WHERE id = ? AND deleted_at IS NULL",
+            [&req.class_id],
+            |row| row.get(0),
+        )?;
+
+        tx.execute(
+            "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, req.name, req.student_number, req.points, req.class_id, class_name, created_at_str],
+        )?;
+
+        if req.points != 0 {
+            record_point_transaction(&tx, PointChange {
+                student_id: &id,
+                class_id: &req.class_id,
+                delta: req.points,
+                balance_after: req.points,
+                reason: Some("初始积分"),
+                source: POINT_SOURCE_OPENING,
+                related_id: None,
+                reason_id: None,
+            })?;
+        }
+
+        // Update class student count
+        tx.execute(
+            "UPDATE classes SET student_count = (SELECT COUNT(*) FROM students WHERE class_id = ? AND deleted_at IS NULL) WHERE id = ?",
+            params![req.class_id, req.class_id],
+        )?;
+
+        let student = Student {
+            id,
+            name: req.name,
+            student_number: req.student_number,
+            points: req.points,
+            class_id: req.class_id,
+            class_name,
+            created_at,
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_STUDENT,
+            entity_id: &student.id,
+            class_id: Some(&student.class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&student),
+        })?;
+
+        tx.commit()?;
+        Ok(student)
+    }
+
+    /// Imports a roster workbook. Rows are validated one by one; every valid row is inserted
+    /// in a single transaction and the rest come back in the report.
+    pub fn import_students_xlsx(&self, class_id: &str, bytes: Vec<u8>) -> Result<StudentImportReport, AppError> {
+        let roster = student_import::read_student_rows(bytes)?;
+        let mut errors = roster.errors;
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let class_name = live_class_name(&tx, class_id)?;
+
+        let mut students = Vec::new();
+
+        for StudentImportRow { row, student_number, name, points } in roster.rows {
+            let taken: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM students WHERE class_id = ? AND student_number = ? AND deleted_at IS NULL)",
+                params![class_id, student_number],
+                |row| row.get(0),
+            )?;
+            if taken {
+                errors.push(StudentImportError {
+                    row,
+                    field: student_import::FIELD_STUDENT_NUMBER.to_string(),
+                    value: student_number,
+                    message: "班级中已存在该学号".to_string(),
+                });
+                continue;
+            }
+
+            students.push(insert_imported_student(&tx, class_id, &class_name, &student_number, &name, points.unwrap_or_default())?);
+        }
+
+        refresh_student_count(&tx, class_id)?;
+        tx.commit()?;
+
+        errors.sort_by_key(|e| e.row);
+        Ok(StudentImportReport {
+            total_rows: roster.total_rows as i32,
+            imported_count: students.len() as i32,
+            students,
+            errors,
+        })
+    }
+
+    /// Dry run of a roster import: matches each valid row to the class by student number
+    /// and classifies it, without writing anything.
+    pub fn preview_student_import(&self, class_id: &str, bytes: Vec<u8>) -> Result<StudentImportPreview, AppError> {
+        let roster = student_import::read_student_rows(bytes)?;
+
+        let conn = self.conn.lock().unwrap();
+        live_class_name(&conn, class_id)?;
+
+        let mut preview = StudentImportPreview {
+            total_rows: roster.total_rows as i32,
+            rows: Vec::new(),
+            errors: roster.errors,
+            create_count: 0,
+            update_count: 0,
+            unchanged_count: 0,
+            conflict_count: 0,
+        };
+
+        for row in roster.rows {
+            let plan = plan_import_row(&conn, class_id, row)?;
+            match plan.action.as_str() {
+                IMPORT_ACTION_CREATE => preview.create_count += 1,
+                IMPORT_ACTION_UPDATE => preview.update_count += 1,
+                IMPORT_ACTION_UNCHANGED => preview.unchanged_count += 1,
+                _ => preview.conflict_count += 1,
+            }
+            preview.rows.push(plan);
+        }
+
+        Ok(preview)
+    }
+
+    /// Applies the `create` and `update` rows of a plan from `preview_student_import`; other
+    /// rows are skipped. Each row is re-planned first, and the whole commit is refused if the
+    /// class changed since the preview.
+    pub fn commit_student_import(&self, class_id: &str, req: CommitStudentImportRequest) -> Result<CommitStudentImportResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let class_name = live_class_name(&tx, class_id)?;
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+
+        let mut result = CommitStudentImportResult {
+            created: 0,
+            updated: 0,
+            skipped: 0,
+            students: Vec::new(),
+        };
+
+        for planned in req.rows {
+            if planned.action != IMPORT_ACTION_CREATE && planned.action != IMPORT_ACTION_UPDATE {
+                result.skipped += 1;
+                continue;
+            }
+
+            let current = plan_import_row(&tx, class_id, StudentImportRow {
+                row: planned.row,
+                student_number: planned.student_number.clone(),
+                name: planned.name.clone(),
+                points: planned.points,
+            })?;
+            if current.action != planned.action || current.student_id != planned.student_id {
+                return Err(AppError::conflict(format!("第 {} 行对应的学生在预览后已发生变化，请重新预览", planned.row)));
+            }
+
+            if current.action == IMPORT_ACTION_CREATE {
+                let student = insert_imported_student(
+                    &tx,
+                    class_id,
+                    &class_name,
+                    &current.student_number,
+                    &current.name,
+                    current.points.unwrap_or_default(),
+                )?;
+                result.students.push(student);
+                result.created += 1;
+                continue;
+            }
+
+            let student_id = current.student_id.as_deref().ok_or_else(|| AppError::not_found("学生不存在"))?;
+            let before = query_student(&tx, student_id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+
+            tx.execute(
+                "UPDATE students SET name = ? WHERE id = ?",
+                params![current.name, student_id],
+            )?;
+
+            // The roster states a balance; it is reached by a delta so the floor still applies
+            if let Some(points) = current.points.filter(|points| *points != before.points) {
+                apply_point_delta(&tx, student_id, points - before.points, min_points, Some("导入名单更新积分"), None, POINT_SOURCE_IMPORT)?;
+            }
+
+            let student = query_student(&tx, student_id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+            write_audit_log(&tx, AuditEntry {
+                entity_type: AUDIT_ENTITY_STUDENT,
+                entity_id: student_id,
+                class_id: Some(class_id),
+                action: AUDIT_ACTION_IMPORT,
+                before: to_json(&before),
+                after: to_json(&student),
+            })?;
+
+            result.students.push(student);
+            result.updated += 1;
+        }
+
+        refresh_student_count(&tx, class_id)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    pub fn update_student(&self, id: &str, req: UpdateStudentRequest) -> Result<Student, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        // Get current student info
+        let current_student = live_student(&tx, id)?;
+
+        // Update fields individually
+        let mut has_updates = false;
+
+        if let Some(name) = &req.name {
+            tx.execute(
+                "UPDATE students SET name = ? WHERE id = ?",
+                params![name, id],
+            )?;
+            has_updates = true;
+        }
+
+        if let Some(student_number) = &req.student_number {
+            tx.execute(
+                "UPDATE students SET student_number = ? WHERE id = ?",
+                params![student_number, id],
+            )?;
+            has_updates = true;
+        }
+
+        // Changing class is a transfer; history follows the student as with transfer_student's default
+        if let Some(class_id) = &req.class_id {
+            if class_id != &current_student.class_id {
+                has_updates = true;
+                move_student(&tx, &current_student, class_id, TRANSFER_HISTORY_CARRY)?;
+            }
+        }
+
+        if !has_updates {
+            return Ok(current_student);
+        }
+
+        // Get updated student
+        let student = query_student(&tx, id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_STUDENT,
+            entity_id: id,
+            class_id: Some(&student.class_id),
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&current_student),
+            after: to_json(&student),
+        })?;
+
+        tx.commit()?;
+        Ok(student)
+    }
+
+    /// Moves a student to another class in one transaction. `history_policy` is `carry`
+    /// (default) or `split`; see `move_student`.
+    pub fn transfer_student(&self, id: &str, req: TransferStudentRequest) -> Result<TransferStudentResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = live_student(&tx, id)?;
+        let history_policy = req.history_policy.as_deref().unwrap_or(TRANSFER_HISTORY_CARRY);
+
+        let transfer = move_student(&tx, &before, &req.to_class_id, history_policy)?;
+        let student = query_student(&tx, id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_STUDENT,
+            entity_id: id,
+            class_id: Some(&student.class_id),
+            action: AUDIT_ACTION_TRANSFER,
+            before: to_json(&before),
+            after: to_json(&student),
+        })?;
+
+        tx.commit()?;
+        Ok(TransferStudentResult { student, transfer })
+    }
+
+    pub fn get_student_transfers(&self, student_id: &str) -> Result<Vec<StudentTransfer>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, student_id, from_class_id, to_class_id, history_policy, purchase_records_moved, point_transactions_moved, created_at
+             FROM student_transfers
+             WHERE student_id = ?
+             ORDER BY created_at DESC",
+        )?;
+
+        let transfer_iter = stmt.query_map([student_id], |row| {
+            let created_at_str: String = row.get(7)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+
+            Ok(StudentTransfer {
+                id: row.get(0)?,
+                student_id: row.get(1)?,
+                from_class_id: row.get(2)?,
+                to_class_id: row.get(3)?,
+                history_policy: row.get(4)?,
+                purchase_records_moved: row.get(5)?,
+                point_transactions_moved: row.get(6)?,
+                created_at,
+            })
+        })?;
+
+        let mut transfers = Vec::new();
+        for transfer in transfer_iter {
+            transfers.push(transfer?);
+        }
+
+        Ok(transfers)
+    }
+
+    pub fn delete_student(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        // Get student before deleting
+        let before = query_student(&tx, id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+
+        // Move student to the recycle bin
+        let rows = tx.execute(
+            "UPDATE students SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        if rows == 0 {
+            return Err(AppError::not_found("学生不存在"));
+        }
+
+        // Update class student count
+        refresh_student_count(&tx, &before.class_id)?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_STUDENT,
+            entity_id: id,
+            class_id: Some(&before.class_id),
+            action: AUDIT_ACTION_DELETE,
+            before: to_json(&before),
+            after: None,
+        })?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Point adjustments
+    pub fn adjust_student_points(&self, student_id: &str, delta: i32, reason: Option<&str>, reason_id: Option<&str>) -> Result<PointAdjustmentResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+        let reason_label = point_reason_label(&tx, reason_id, &[student_id])?;
+        let reason = reason.or(reason_label.as_deref());
+        let result = apply_point_delta(&tx, student_id, delta, min_points, reason, reason_id, POINT_SOURCE_MANUAL)?;
+
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Applies the same delta to a list of students or a whole class. Either every
+    /// student is adjusted or, if any one of them would break the floor, none are.
+    pub fn batch_adjust_points(&self, req: BatchAdjustPointsRequest) -> Result<BatchAdjustPointsResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let student_ids: Vec<String> = match (&req.student_ids, &req.class_id) {
+            (Some(student_ids), _) if !student_ids.is_empty() => {
+                let mut unique_ids: Vec<String> = Vec::new();
+                for student_id in student_ids {
+                    if !unique_ids.contains(student_id) {
+                        unique_ids.push(student_id.clone());
+                    }
+                }
+                unique_ids
+            }
+            (_, Some(class_id)) => {
+                let mut stmt = tx.prepare("SELECT id FROM students WHERE class_id = ? AND deleted_at IS NULL ORDER BY CAST(student_number AS INTEGER) ASC")?;
+                let id_iter = stmt.query_map([class_id], |row| row.get(0))?;
+
+                let mut ids = Vec::new();
+                for id in id_iter {
+                    ids.push(id?);
+                }
+                ids
+            }
+            _ => return Err(AppError::invalid_input("请选择学生或班级")),
+        };
+
+        if student_ids.is_empty() {
+            return Err(AppError::invalid_input("没有可调整积分的学生"));
+        }
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+        let student_id_refs: Vec<&str> = student_ids.iter().map(String::as_str).collect();
+        let reason_label = point_reason_label(&tx, req.reason_id.as_deref(), &student_id_refs)?;
+        let reason = req.reason.as_deref().or(reason_label.as_deref());
+
+        // Every row of the batch shares one operation id, so undo treats it as one action
+        let operation_id = Uuid::new_v4().to_string();
+        let mut results = Vec::new();
+        let mut failures = Vec::new();
+        for student_id in &student_ids {
+            match apply_point_delta(&tx, student_id, req.delta, min_points, reason, req.reason_id.as_deref(), POINT_SOURCE_MANUAL) {
+                Ok(result) => {
+                    tx.execute(
+                        "UPDATE point_transactions SET operation_id = ?1 WHERE id = ?2",
+                        params![operation_id, result.transaction_id],
+                    )?;
+                    results.push(result);
+                }
+                Err(e) => failures.push((student_id, e)),
+            }
+        }
+
+        // Reported with the first failure's code so callers can still branch on it
+        if let Some((_, first)) = failures.first() {
+            let messages: Vec<&str> = failures.iter().map(|(_, e)| e.message.as_str()).collect();
+            let details: Vec<serde_json::Value> = failures
+                .iter()
+                .map(|(student_id, e)| serde_json::json!({ "studentId": student_id, "code": e.code, "message": e.message }))
+                .collect();
+            return Err(AppError::new(first.code, format!("批量调整失败，未做任何修改：{}", messages.join("；")))
+                .with_details(serde_json::Value::Array(details)));
+        }
+
+        tx.commit()?;
+
+        Ok(BatchAdjustPointsResult {
+            operation_id,
+            total_students: results.len() as i32,
+            results,
+        })
+    }
+
+    /// Undoes the operation a ledger row belongs to. A row from a batch adjustment takes the
+    /// whole batch with it, so one student's history can never split a batch apart.
+    pub fn undo_point_operation(&self, transaction_id: &str) -> Result<Vec<PointTransaction>, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let operation_id: Option<String> = tx
+            .query_row("SELECT operation_id FROM point_transactions WHERE id = ?", [transaction_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::not_found("积分记录不存在"))?;
+
+        let transaction_ids = match operation_id {
+            Some(operation_id) => {
+                let transaction_ids = collect_rows(
+                    &tx,
+                    "SELECT id FROM point_transactions
+                     WHERE operation_id = ?1 AND reverts_id IS NULL AND reverted_by IS NULL
+                     ORDER BY rowid DESC",
+                    [&operation_id],
+                    |row| row.get(0),
+                )?;
+                if transaction_ids.is_empty() {
+                    return Err(AppError::conflict("该积分记录已撤销"));
+                }
+                transaction_ids
+            }
+            None => vec![transaction_id.to_string()],
+        };
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+
+        let mut reversals = Vec::new();
+        for transaction_id in &transaction_ids {
+            reversals.push(undo_point_transaction(&tx, transaction_id, min_points)?);
+        }
+
+        tx.commit()?;
+        Ok(reversals)
+    }
+
+    /// Undoes the class's `count` most recent manual operations that have not been undone yet.
+    /// A batch adjustment counts as one operation however many students it touched.
+    pub fn undo_last_point_operations(&self, class_id: &str, count: i64) -> Result<Vec<PointTransaction>, AppError> {
+        if count <= 0 {
+            return Err(AppError::invalid_input("撤销条数必须大于0"));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let transaction_ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "WITH undoable AS (
+                     SELECT id, COALESCE(operation_id, id) AS operation, created_at, rowid AS seq
+                     FROM point_transactions
+                     WHERE class_id = ?1 AND source = ?2 AND reverts_id IS NULL AND reverted_by IS NULL
+                 ),
+                 latest_operations AS (
+                     SELECT operation, MAX(created_at) AS created_at, MAX(seq) AS seq
+                     FROM undoable
+                     GROUP BY operation
+                     ORDER BY created_at DESC, seq DESC
+                     LIMIT ?3
+                 )
+                 SELECT undoable.id FROM undoable
+                 JOIN latest_operations ON latest_operations.operation = undoable.operation
+                 ORDER BY latest_operations.created_at DESC, latest_operations.seq DESC, undoable.seq DESC",
+            )?;
+            let id_iter = stmt.query_map(params![class_id, POINT_SOURCE_MANUAL, count], |row| row.get(0))?;
+
+            let mut ids = Vec::new();
+            for id in id_iter {
+                ids.push(id?);
+            }
+            ids
+        };
+
+        if transaction_ids.is_empty() {
+            return Err(AppError::conflict("没有可撤销的积分操作"));
+        }
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+
+        let mut reversals = Vec::new();
+        for transaction_id in &transaction_ids {
+            reversals.push(undo_point_transaction(&tx, transaction_id, min_points)?);
+        }
+
+        tx.commit()?;
+        Ok(reversals)
+    }
+
+    pub fn get_point_settings(&self) -> Result<PointSettings, AppError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(PointSettings {
+            min_points: get_setting_i32(&conn, SETTING_MIN_POINTS, 0)?,
+        })
+    }
+
+    pub fn update_point_settings(&self, settings: PointSettings) -> Result<PointSettings, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = PointSettings {
+            min_points: get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?,
+        };
+        set_setting(&tx, SETTING_MIN_POINTS, &settings.min_points.to_string())?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_SETTING,
+            entity_id: SETTING_MIN_POINTS,
+            class_id: None,
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&before),
+            after: to_json(&settings),
+        })?;
+
+        tx.commit()?;
+        Ok(settings)
+    }
+
+    // Point reason catalog
+    pub fn get_point_reasons_by_class(&self, class_id: &str) -> Result<Vec<PointReason>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, class_id, label, default_delta, category, sort_order, created_at
+             FROM point_reasons
+             WHERE class_id = ?
+             ORDER BY sort_order ASC, created_at ASC",
+        )?;
+
+        let reason_iter = stmt.query_map([class_id], row_to_point_reason)?;
+
+        let mut reasons = Vec::new();
+        for reason in reason_iter {
+            reasons.push(reason?);
+        }
+
+        Ok(reasons)
+    }
+
+    pub fn create_point_reason(&self, req: CreatePointReasonRequest) -> Result<PointReason, AppError> {
+        if req.label.trim().is_empty() {
+            return Err(AppError::invalid_input("原因名称不能为空"));
+        }
+        let category = resolve_point_reason_category(req.category.as_deref(), req.default_delta)?;
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let class_exists: Option<String> = tx
+            .query_row("SELECT id FROM classes WHERE id = ? AND deleted_at IS NULL", [&req.class_id], |row| row.get(0))
+            .optional()?;
+        if class_exists.is_none() {
+            return Err(AppError::not_found("班级不存在"));
+        }
+
+        let sort_order = match req.sort_order {
+            Some(sort_order) => sort_order,
+            None => tx.query_row(
+                "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM point_reasons WHERE class_id = ?",
+                [&req.class_id],
+                |row| row.get(0),
+            )?,
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+
+        tx.execute(
+            "INSERT INTO point_reasons (id, class_id, label, default_delta, category, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, req.class_id, req.label.trim(), req.default_delta, category, sort_order, created_at.to_rfc3339()],
+        )?;
+
+        let reason = PointReason {
+            id,
+            class_id: req.class_id,
+            label: req.label.trim().to_string(),
+            default_delta: req.default_delta,
+            category,
+            sort_order,
+            created_at,
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_POINT_REASON,
+            entity_id: &reason.id,
+            class_id: Some(&reason.class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&reason),
+        })?;
+
+        tx.commit()?;
+        Ok(reason)
+    }
+
+    pub fn update_point_reason(&self, id: &str, req: UpdatePointReasonRequest) -> Result<PointReason, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let current: PointReason = tx
+            .query_row(
+                "SELECT id, class_id, label, default_delta, category, sort_order, created_at FROM point_reasons WHERE id = ?",
+                [id],
+                row_to_point_reason,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("积分原因不存在"))?;
+
+        if req.label.is_none() && req.default_delta.is_none() && req.category.is_none() && req.sort_order.is_none() {
+            return Err(AppError::invalid_input("No fields to update"));
+        }
+
+        let label = req.label.as_deref().map(str::trim).unwrap_or(&current.label).to_string();
+        if label.is_empty() {
+            return Err(AppError::invalid_input("原因名称不能为空"));
+        }
+        let default_delta = req.default_delta.unwrap_or(current.default_delta);
+        let category = match (&req.category, req.default_delta) {
+            (None, None) => current.category.clone(),
+            (category, _) => resolve_point_reason_category(category.as_deref(), default_delta)?,
+        };
+        let sort_order = req.sort_order.unwrap_or(current.sort_order);
+
+        tx.execute(
+            "UPDATE point_reasons SET label = ?1, default_delta = ?2, category = ?3, sort_order = ?4 WHERE id = ?5",
+            params![label, default_delta, category, sort_order, id],
+        )?;
+
+        let reason = PointReason {
+            label,
+            default_delta,
+            category,
+            sort_order,
+            ..current.clone()
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_POINT_REASON,
+            entity_id: id,
+            class_id: Some(&reason.class_id),
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&current),
+            after: to_json(&reason),
+        })?;
+
+        tx.commit()?;
+        Ok(reason)
+    }
+
+    pub fn delete_point_reason(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before: PointReason = tx
+            .query_row(
+                "SELECT id, class_id, label, default_delta, category, sort_order, created_at FROM point_reasons WHERE id = ?",
+                [id],
+                row_to_point_reason,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("积分原因不存在"))?;
+
+        // Ledger rows keep the label in `reason`; only the link to the catalog goes
+        tx.execute("UPDATE point_transactions SET reason_id = NULL WHERE reason_id = ?", [id])?;
+        tx.execute("DELETE FROM point_reasons WHERE id = ?", [id])?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_POINT_REASON,
+            entity_id: id,
+            class_id: Some(&before.class_id),
+            action: AUDIT_ACTION_DELETE,
+            before: to_json(&before),
+            after: None,
+        })?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Point ledger
+    pub fn get_student_point_transactions(&self, student_id: &str) -> Result<Vec<PointTransaction>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, student_id, class_id, delta, balance_after, reason, source, related_id, created_at, reason_id, reverts_id, reverted_by, operation_id
+             FROM point_transactions
+             WHERE student_id = ?
+             ORDER BY created_at DESC, rowid DESC",
+        )?;
+
+        let transaction_iter = stmt.query_map([student_id], row_to_point_transaction)?;
+
+        let mut transactions = Vec::new();
+        for transaction in transaction_iter {
+            transactions.push(transaction?);
+        }
+
+        Ok(transactions)
+    }
+
+    pub fn get_point_transactions_paginated(&self, class_id: &str, page: i64, page_size: i64, student_id: Option<&str>) -> Result<PaginatedPointTransactions, AppError> {
+        validate_page(page, page_size)?;
+        let conn = self.conn.lock().unwrap();
+
+        let student_value = student_id.filter(|value| !value.trim().is_empty()).unwrap_or("");
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM point_transactions WHERE class_id = ?1 AND (?2 = '' OR student_id = ?2)",
+            params![class_id, student_value],
+            |row| row.get(0),
+        )?;
+
+        let total_pages = (total + page_size - 1) / page_size;
+        let offset = (page - 1) * page_size;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, student_id, class_id, delta, balance_after, reason, source, related_id, created_at, reason_id, reverts_id, reverted_by, operation_id
+             FROM point_transactions
+             WHERE class_id = ?1 AND (?2 = '' OR student_id = ?2)
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ?3 OFFSET ?4",
+        )?;
+
+        let transaction_iter = stmt.query_map(params![class_id, student_value, page_size, offset], row_to_point_transaction)?;
+
+        let mut transactions = Vec::new();
+        for transaction in transaction_iter {
+            transactions.push(transaction?);
+        }
+
+        Ok(PaginatedPointTransactions {
+            transactions,
+            total,
+            total_pages,
+            current_page: page,
+            page_size,
+        })
+    }
+
+    // Product CRUD operations
+    pub fn get_products_by_class(&self, class_id: &str) -> Result<Vec<Product>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, name, points, stock, class_id, created_at FROM products WHERE class_id = ? AND deleted_at IS NULL ORDER BY created_at DESC")?;
+
+        let product_iter = stmt.query_map([class_id], |row| {
+            let created_at_str: String = row.get(5)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+
+            Ok(Product {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                points: row.get(2)?,
+                stock: row.get(3)?,
+                class_id: row.get(4)?,
+                created_at,
+            })
+        })?;
+
+        let mut products = Vec::new();
+        for product in product_iter {
+            products.push(product?);
+        }
+
+        Ok(products)
+    }
+
+    pub fn create_product(&self, req: CreateProductRequest) -> Result<Product, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        live_class(&tx, &req.class_id)?;
+        let id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        let created_at_str = created_at.to_rfc3339();
+
+        tx.execute(
+            "INSERT INTO products (id, name, points, stock, class_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, req.name, req.points, req.stock, req.class_id, created_at_str],
+        )?;
+
+        let product = Product {
+            id,
+            name: req.name,
+            points: req.points,
+            stock: req.stock,
+            class_id: req.class_id,
+            created_at,
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PRODUCT,
+            entity_id: &product.id,
+            class_id: Some(&product.class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&product),
+        })?;
+
+        tx.commit()?;
+        Ok(product)
+    }
+
+    pub fn update_product(&self, id: &str, req: UpdateProductRequest) -> Result<Product, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = live_product(&tx, id)?;
+
+        let mut has_updates = false;
+
+        if let Some(name) = &req.name {
+            tx.execute(
+                "UPDATE products SET name = ? WHERE id = ?",
+                params![name, id],
+            )?;
+            has_updates = true;
+        }
+
+        if let Some(points) = req.points {
+            tx.execute(
+                "UPDATE products SET points = ? WHERE id = ?",
+                params![points, id],
+            )?;
+            has_updates = true;
+        }
+
+        if let Some(stock) = req.stock {
+            tx.execute(
+                "UPDATE products SET stock = ? WHERE id = ?",
+                params![stock, id],
+            )?;
+            has_updates = true;
+        }
+
+        if !has_updates {
+            return Err(AppError::invalid_input("No fields to update"));
+        }
+
+        // Get updated product
+        let product = query_product(&tx, id)?.ok_or_else(|| AppError::not_found("商品不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PRODUCT,
+            entity_id: id,
+            class_id: Some(&product.class_id),
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&before),
+            after: to_json(&product),
+        })?;
+
+        tx.commit()?;
+        Ok(product)
+    }
+
+    /// Archives the product. A product still on a wheel or in pending orders is only
+    /// archived with `force`, which also takes it off the wheel so the odds change visibly.
+    pub fn delete_product(&self, id: &str, force: bool) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = live_product(&tx, id)?;
+
+        let slot_count: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM wheel_slots WHERE product_id = ?",
+            [id],
+            |row| row.get(0),
+        )?;
+        let pending_count: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM purchase_records WHERE product_id = ?1 AND shipping_status IN (?2, ?3)",
+            params![id, SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY],
+            |row| row.get(0),
+        )?;
+
+        if !force && (slot_count > 0 || pending_count > 0) {
+            let mut references = Vec::new();
+            if slot_count > 0 {
+                references.push(format!("转盘格子 {} 个", slot_count));
+            }
+            if pending_count > 0 {
+                references.push(format!("待发货购买记录 {} 条", pending_count));
+            }
+            return Err(AppError::constraint_violation(format!("商品仍被引用：{}。确认后将移除转盘格子并归档商品", references.join("、")))
+                .with_details(serde_json::json!({ "wheelSlots": slot_count, "pendingRecords": pending_count })));
+        }
+
+        if slot_count > 0 {
+            let wheel_before = wheel_config_snapshot(&tx, &before.class_id)?;
+
+            tx.execute("DELETE FROM wheel_slots WHERE product_id = ?", [id])?;
+
+            // Close the gaps left in slot_index, renumbering in order so no two slots share an index
+            let slot_ids: Vec<String> = {
+                let mut stmt = tx.prepare("SELECT id FROM wheel_slots WHERE class_id = ? ORDER BY slot_index ASC")?;
+                let id_iter = stmt.query_map([&before.class_id], |row| row.get(0))?;
+
+                let mut ids = Vec::new();
+                for slot_id in id_iter {
+                    ids.push(slot_id?);
+                }
+                ids
+            };
+            for (slot_index, slot_id) in slot_ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE wheel_slots SET slot_index = ?1 WHERE id = ?2",
+                    params![slot_index as i32, slot_id],
+                )?;
+            }
+
+            write_audit_log(&tx, AuditEntry {
+                entity_type: AUDIT_ENTITY_WHEEL_CONFIG,
+                entity_id: &before.class_id,
+                class_id: Some(&before.class_id),
+                action: AUDIT_ACTION_UPDATE,
+                before: wheel_before,
+                after: wheel_config_snapshot(&tx, &before.class_id)?,
+            })?;
+        }
+
+        let rows = tx.execute(
+            "UPDATE products SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        if rows == 0 {
+            return Err(AppError::not_found("商品不存在"));
+        }
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PRODUCT,
+            entity_id: id,
+            class_id: Some(&before.class_id),
+            action: AUDIT_ACTION_DELETE,
+            before: to_json(&before),
+            after: None,
+        })?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Recycle bin
+    pub fn list_trash(&self) -> Result<Vec<TrashItem>, AppError> {
+        let conn = self.conn.lock().unwrap();
+
+        // Students and products trashed together with their class are listed (and restored) through the class.
+        let mut stmt = conn.prepare(
+            "SELECT 'class', id, name, id, name, deleted_at
+             FROM classes
+             WHERE deleted_at IS NOT NULL
+             UNION ALL
+             SELECT 'student', s.id, s.name, s.class_id, c.name, s.deleted_at
+             FROM students s
+             LEFT JOIN classes c ON c.id = s.class_id
+             WHERE s.deleted_at IS NOT NULL AND (c.deleted_at IS NULL OR c.deleted_at <> s.deleted_at)
+             UNION ALL
+             SELECT 'product', p.id, p.name, p.class_id, c.name, p.deleted_at
+             FROM products p
+             LEFT JOIN classes c ON c.id = p.class_id
+             WHERE p.deleted_at IS NOT NULL AND (c.deleted_at IS NULL OR c.deleted_at <> p.deleted_at)
+             ORDER BY 6 DESC",
+        )?;
+
+        let item_iter = stmt.query_map([], |row| {
+            let deleted_at_str: String = row.get(5)?;
+            let deleted_at = DateTime::parse_from_rfc3339(&deleted_at_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+
+            Ok(TrashItem {
+                entity_type: row.get(0)?,
+                id: row.get(1)?,
+                name: row.get(2)?,
+                class_id: row.get(3)?,
+                class_name: row.get(4)?,
+                deleted_at,
+            })
+        })?;
+
+        let mut items = Vec::new();
+        for item in item_iter {
+            items.push(item?);
+        }
+
+        Ok(items)
+    }
+
+    pub fn restore_class(&self, id: &str) -> Result<Class, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let deleted_at: Option<String> = tx
+            .query_row("SELECT deleted_at FROM classes WHERE id = ?", [id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::not_found("班级不存在"))?;
+        let deleted_at = deleted_at.ok_or_else(|| AppError::conflict("班级不在回收站中"))?;
+
+        tx.execute(
+            "UPDATE students SET deleted_at = NULL WHERE class_id = ?1 AND deleted_at = ?2",
+            params![id, deleted_at],
+        )?;
+        tx.execute(
+            "UPDATE products SET deleted_at = NULL WHERE class_id = ?1 AND deleted_at = ?2",
+            params![id, deleted_at],
+        )?;
+        tx.execute("UPDATE classes SET deleted_at = NULL WHERE id = ?", [id])?;
+        refresh_student_count(&tx, id)?;
+
+        let class = query_class(&tx, id)?.ok_or_else(|| AppError::not_found("班级不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_CLASS,
+            entity_id: id,
+            class_id: Some(id),
+            action: AUDIT_ACTION_RESTORE,
+            before: None,
+            after: to_json(&class),
+        })?;
+
+        tx.commit()?;
+        Ok(class)
+    }
+
+    pub fn restore_student(&self, id: &str) -> Result<Student, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let (deleted_at, class_deleted_at): (Option<String>, Option<String>) = tx
+            .query_row(
+                "SELECT s.deleted_at, c.deleted_at FROM students s LEFT JOIN classes c ON c.id = s.class_id WHERE s.id = ?",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("学生不存在"))?;
+        if deleted_at.is_none() {
+            return Err(AppError::conflict("学生不在回收站中"));
+        }
+        if class_deleted_at.is_some() {
+            return Err(AppError::constraint_violation("学生所在班级已删除，请先恢复班级"));
+        }
+
+        tx.execute("UPDATE students SET deleted_at = NULL WHERE id = ?", [id])?;
+
+        let student = query_student(&tx, id)?.ok_or_else(|| AppError::not_found("学生不存在"))?;
+        refresh_student_count(&tx, &student.class_id)?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_STUDENT,
+            entity_id: id,
+            class_id: Some(&student.class_id),
+            action: AUDIT_ACTION_RESTORE,
+            before: None,
+            after: to_json(&student),
+        })?;
+
+        tx.commit()?;
+        Ok(student)
+    }
+
+    pub fn restore_product(&self, id: &str) -> Result<Product, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let (deleted_at, class_deleted_at): (Option<String>, Option<String>) = tx
+            .query_row(
+                "SELECT p.deleted_at, c.deleted_at FROM products p LEFT JOIN classes c ON c.id = p.class_id WHERE p.id = ?",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("商品不存在"))?;
+        if deleted_at.is_none() {
+            return Err(AppError::conflict("商品不在回收站中"));
+        }
+        if class_deleted_at.is_some() {
+            return Err(AppError::constraint_violation("商品所在班级已删除，请先恢复班级"));
+        }
+
+        tx.execute("UPDATE products SET deleted_at = NULL WHERE id = ?", [id])?;
+
+        let product = query_product(&tx, id)?.ok_or_else(|| AppError::not_found("商品不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PRODUCT,
+            entity_id: id,
+            class_id: Some(&product.class_id),
+            action: AUDIT_ACTION_RESTORE,
+            before: None,
+            after: to_json(&product),
+        })?;
+
+        tx.commit()?;
+        Ok(product)
+    }
+
+    /// Permanently deletes trashed items. With `older_than_days`, only items that have
+    /// been in the recycle bin at least that long are removed. Products that purchase
+    /// records still point to stay archived so that history keeps resolving.
+    pub fn purge_trash(&self, older_than_days: Option<i64>) -> Result<PurgeTrashResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        self.auto_backup(&conn, backup::BACKUP_KIND_PRE_PURGE)?;
+        let tx = conn.transaction()?;
+
+        let cutoff = (Utc::now() - chrono::Duration::days(older_than_days.unwrap_or(0).max(0))).to_rfc3339();
+
+        let select_ids = |sql: &str| -> SqliteResult<Vec<(String, Option<String>)>> {
+            let mut stmt = tx.prepare(sql)?;
+            let id_iter = stmt.query_map([&cutoff], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+            let mut ids = Vec::new();
+            for id in id_iter {
+                ids.push(id?);
+            }
+            Ok(ids)
+        };
+
+        let class_ids = select_ids("SELECT id, id FROM classes WHERE deleted_at IS NOT NULL AND deleted_at <= ?")?;
+        for (class_id, _) in &class_ids {
+            purge_class(&tx, class_id)?;
+        }
+
+        let student_ids = select_ids("SELECT id, class_id FROM students WHERE deleted_at IS NOT NULL AND deleted_at <= ?")?;
+        for (student_id, _) in &student_ids {
+            purge_student(&tx, student_id)?;
+        }
+
+        let product_ids = select_ids(
+            "SELECT id, class_id FROM products
+             WHERE deleted_at IS NOT NULL AND deleted_at <= ?
+               AND NOT EXISTS (SELECT 1 FROM purchase_records r WHERE r.product_id = products.id)",
+        )?;
+        for (product_id, _) in &product_ids {
+            purge_product(&tx, product_id)?;
+        }
+
+        let purged = class_ids
+            .iter()
+            .map(|(id, class_id)| (AUDIT_ENTITY_CLASS, id, class_id))
+            .chain(student_ids.iter().map(|(id, class_id)| (AUDIT_ENTITY_STUDENT, id, class_id)))
+            .chain(product_ids.iter().map(|(id, class_id)| (AUDIT_ENTITY_PRODUCT, id, class_id)));
+        for (entity_type, entity_id, class_id) in purged {
+            write_audit_log(&tx, AuditEntry {
+                entity_type,
+                entity_id,
+                class_id: class_id.as_deref(),
+                action: AUDIT_ACTION_PURGE,
+                before: None,
+                after: None,
+            })?;
+        }
+
+        tx.commit()?;
+
+        Ok(PurgeTrashResult {
+            classes: class_ids.len() as i32,
+            students: student_ids.len() as i32,
+            products: product_ids.len() as i32,
+        })
+    }
+
+    // Purchase records methods
+    pub fn create_purchase_record(&self, req: CreatePurchaseRequest) -> Result<PurchaseRecord, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+
+        // Start transaction
+        let tx = conn.transaction()?;
+
+        // Get product and student info
+        let product: Product = tx.query_row(
+            "SELECT id, name, points, stock, class_id, created_at FROM products WHERE id = ? AND deleted_at IS NULL",
+            [&req.product_id],
+            |row| {
+                Ok(Product {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    points: row.get(2)?,
+                    stock: row.get(3)?,
+                    class_id: row.get(4)?,
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?).map_err(|_| rusqlite::Error::InvalidColumnType(5, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
+                })
+            },
+        )?;
+
+        let student: Student = tx.query_row(
+            "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students WHERE id = ? AND deleted_at IS NULL",
+            [&req.student_id],
+            |row| {
+                Ok(Student {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    student_number: row.get(2)?,
+                    points: row.get(3)?,
+                    class_id: row.get(4)?,
+                    class_name: row.get(5)?,
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?).map_err(|_| rusqlite::Error::InvalidColumnType(6, "datetime".to_string(), rusqlite::types::Type::Text))?.with_timezone(&Utc),
+                })
+            },
+        )?;
+
+        if req.quantity <= 0 {
+            return Err(AppError::invalid_input("兑换数量必须大于0"));
+        }
+
+        // Check if product has enough stock
+        if product.stock < req.quantity {
+            return Err(AppError::insufficient_stock("库存不足"));
+        }
+
+        let total_points = product
+            .points
+            .checked_mul(req.quantity)
+            .ok_or_else(|| AppError::invalid_input("兑换所需积分超出范围"))?;
+
+        // Create purchase record
+        let id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+
+        tx.execute(
+            "INSERT INTO purchase_records (id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &id,
+                &product.id,
+                &product.name,
+                &total_points,
+                &student.id,
+                &student.name,
+                &req.quantity,
+                &product.class_id,
+                &created_at.to_rfc3339(),
+                SHIPPING_STATUS_PENDING,
+                "购买"
+            ],
+        )?;
+
+        // Deducted through the same floor check as every other debit; free products cost nothing
+        if total_points != 0 {
+            let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+            let reason = format!("兑换 {} x{}", product.name, req.quantity);
+            let debit = apply_point_delta(&tx, &student.id, -total_points, min_points, Some(&reason), None, POINT_SOURCE_PURCHASE)?;
+            tx.execute(
+                "UPDATE point_transactions SET related_id = ?1 WHERE id = ?2",
+                params![id, debit.transaction_id],
+            )?;
+        }
+
+        // Update product stock
+        tx.execute(
+            "UPDATE products SET stock = stock - ? WHERE id = ?",
+            params![&req.quantity, &product.id],
+        )?;
+
+        let record = PurchaseRecord {
+            id,
+            product_id: product.id,
+            product_name: product.name,
+            points: total_points,
+            student_id: student.id,
+            student_name: student.name,
+            quantity: req.quantity,
+            class_id: product.class_id,
+            created_at,
+            shipping_status: SHIPPING_STATUS_PENDING.to_string(),
+            source: "购买".to_string(),
+            refunded_quantity: 0,
+            shipped_at: None,
+            received_at: None,
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PURCHASE_RECORD,
+            entity_id: &record.id,
+            class_id: Some(&record.class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&record),
+        })?;
+
+        // Commit transaction
+        tx.commit()?;
+
+        Ok(record)
+    }
+
+    pub fn get_purchase_records_by_class(&self, class_id: &str) -> Result<Vec<PurchaseRecord>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at FROM purchase_records WHERE class_id = ? ORDER BY created_at DESC")?;
+
+        let records = stmt.query_map([class_id], row_to_purchase_record)?;
+
+        let mut result = Vec::new();
+        for record in records {
+            result.push(record?);
+        }
+
+        Ok(result)
+    }
+
+    pub fn get_purchase_records_paginated(&self, class_id: &str, page: i64, page_size: i64, source: Option<&str>) -> Result<PaginatedPurchaseRecords, AppError> {
+        validate_page(page, page_size)?;
+        let conn = self.conn.lock().unwrap();
+
+        let source_filter = source.filter(|value| !value.trim().is_empty() && *value != "all");
+        let source_value = source_filter.unwrap_or("");
+
+        // 获取总记录数
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM purchase_records WHERE class_id = ?1 AND (?2 = '' OR source = ?2)",
+            params![class_id, source_value],
+            |row| row.get(0),
+        )?;
+
+        // 计算总页数
+        let total_pages = (total + page_size - 1) / page_size;
+
+        // 计算偏移量
+        let offset = (page - 1) * page_size;
+
+        // 分页查询数据
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE class_id = ?1 AND (?2 = '' OR source = ?2)
+             ORDER BY created_at DESC
+             LIMIT ?3 OFFSET ?4"
+        )?;
+
+        let records = stmt.query_map(params![class_id, source_value, page_size, offset], row_to_purchase_record)?;
+
+        let mut result = Vec::new();
+        for record in records {
+            result.push(record?);
+        }
+
+        Ok(PaginatedPurchaseRecords {
+            records: result,
+            total,
+            total_pages,
+            current_page: page,
+            page_size,
+        })
+    }
+
+    pub fn get_wheel_config(&self, class_id: &str) -> Result<WheelConfig, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let now_rfc3339 = Utc::now().to_rfc3339();
+        let class_exists: Option<String> = conn
+            .query_row("SELECT id FROM classes WHERE id = ? AND deleted_at IS NULL", [class_id], |row| row.get(0))
+            .optional()?;
+        if class_exists.is_none() {
+            return Err(AppError::not_found("班级不存在"));
+        }
+
+        let existing: Option<(i32, String, String)> = conn
+            .query_row(
+                "SELECT spin_cost, created_at, updated_at FROM wheel_configs WHERE class_id = ?",
+                [class_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        if existing.is_none() {
+            conn.execute(
+                "INSERT INTO wheel_configs (class_id, spin_cost, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                params![class_id, 10, now_rfc3339, now_rfc3339],
+            )?;
+        }
+
+        let (spin_cost, created_at_raw, updated_at_raw): (i32, String, String) = conn.query_row(
+            "SELECT spin_cost, created_at, updated_at FROM wheel_configs WHERE class_id = ?",
+            [class_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT
+                ws.id,
+                ws.class_id,
+                ws.product_id,
+                p.name,
+                p.points,
+                p.stock,
+                ws.slot_index
+             FROM wheel_slots ws
+             JOIN products p ON p.id = ws.product_id
+             WHERE ws.class_id = ?
+             ORDER BY ws.slot_index ASC",
+        )?;
+
+        let slot_rows = stmt.query_map([class_id], |row| {
+            Ok(WheelSlot {
+                id: row.get(0)?,
+                class_id: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                product_points: row.get(4)?,
+                product_stock: row.get(5)?,
+                slot_index: row.get(6)?,
+            })
+        })?;
+
+        let mut slots = Vec::new();
+        for slot in slot_rows {
+            slots.push(slot?);
+        }
+
+        Ok(WheelConfig {
+            class_id: class_id.to_string(),
+            spin_cost,
+            slots,
+            created_at: DateTime::parse_from_rfc3339(&created_at_raw)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: DateTime::parse_from_rfc3339(&updated_at_raw)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    pub fn save_wheel_config(
+        &self,
+        class_id: &str,
+        req: SaveWheelConfigRequest,
+    ) -> Result<WheelConfig, AppError> {
+        if req.spin_cost <= 0 {
+            return Err(AppError::invalid_input("抽奖消耗积分必须大于0"));
+        }
+        if req.product_ids.is_empty() {
+            return Err(AppError::invalid_input("转盘至少需要一个奖品格子"));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let class_exists: Option<String> = tx
+            .query_row(
+                "SELECT id FROM classes WHERE id = ? AND deleted_at IS NULL",
+                [class_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if class_exists.is_none() {
+            return Err(AppError::not_found("班级不存在"));
+        }
+
+        for product_id in &req.product_ids {
+            let product_exists: Option<String> = tx
+                .query_row(
+                    "SELECT id FROM products WHERE id = ? AND class_id = ? AND deleted_at IS NULL",
+                    params![product_id, class_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if product_exists.is_none() {
+                return Err(AppError::not_found(format!("奖品不存在或不属于当前班级: {}", product_id)));
+            }
+        }
+
+        let before = wheel_config_snapshot(&tx, class_id)?;
+
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO wheel_configs (class_id, spin_cost, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(class_id) DO UPDATE SET
+                spin_cost = excluded.spin_cost,
+                updated_at = excluded.updated_at",
+            params![class_id, req.spin_cost, now, now],
+        )?;
+
+        tx.execute("DELETE FROM wheel_slots WHERE class_id = ?", [class_id])?;
+
+        for (index, product_id) in req.product_ids.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO wheel_slots (id, class_id, product_id, slot_index, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    class_id,
+                    product_id,
+                    index as i32,
+                    now
+                ],
+            )?;
+        }
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_WHEEL_CONFIG,
+            entity_id: class_id,
+            class_id: Some(class_id),
+            action: if before.is_some() { AUDIT_ACTION_UPDATE } else { AUDIT_ACTION_CREATE },
+            before,
+            after: wheel_config_snapshot(&tx, class_id)?,
+        })?;
+
+        tx.commit()?;
+        drop(conn);
+
+        self.get_wheel_config(class_id)
+    }
+
+    pub fn spin_wheel(
+        &self,
+        class_id: &str,
+        req: SpinWheelRequest,
+    ) -> Result<SpinWheelResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let spin_cost: i32 = tx
+            .query_row(
+                "SELECT spin_cost FROM wheel_configs WHERE class_id = ?",
+                [class_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::wheel_not_configured("请先配置大转盘"))?;
+
+        if spin_cost <= 0 {
+            return Err(AppError::wheel_not_configured("抽奖消耗积分配置无效"));
+        }
+
+        let mut slots_stmt = tx.prepare(
+            "SELECT
+                ws.id,
+                ws.class_id,
+                ws.product_id,
+                p.name,
+                p.points,
+                p.stock,
+                ws.slot_index
+             FROM wheel_slots ws
+             JOIN products p ON p.id = ws.product_id
+             WHERE ws.class_id = ?
+             ORDER BY ws.slot_index ASC",
+        )?;
+
+        let slot_rows = slots_stmt.query_map([class_id], |row| {
+            Ok(WheelSlot {
+                id: row.get(0)?,
+                class_id: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                product_points: row.get(4)?,
+                product_stock: row.get(5)?,
+                slot_index: row.get(6)?,
+            })
+        })?;
+
+        let mut slots = Vec::new();
         for slot in slot_rows {
             slots.push(slot?);
         }
+        drop(slots_stmt);
+
+        if slots.is_empty() {
+            return Err(AppError::wheel_not_configured("转盘未配置奖品格子"));
+        }
+
+        if let Some(slot) = slots.iter().find(|slot| slot.product_stock <= 0) {
+            return Err(AppError::insufficient_stock(format!("奖品库存不足，无法开启转盘: {}", slot.product_name)));
+        }
+
+        let student_name: String = tx
+            .query_row(
+                "SELECT name FROM students WHERE id = ? AND class_id = ? AND deleted_at IS NULL",
+                params![req.student_id, class_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("学生不存在或不属于当前班级"))?;
+
+        let winning_index = (Uuid::new_v4().as_u128() % slots.len() as u128) as usize;
+        let mut winning_slot = slots[winning_index].clone();
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+        let reason = format!("大转盘抽中 {}", winning_slot.product_name);
+        let debit = apply_point_delta(&tx, &req.student_id, -spin_cost, min_points, Some(&reason), None, POINT_SOURCE_WHEEL)?;
+        let remaining_points = debit.balance;
+
+        let stock_rows = tx.execute(
+            "UPDATE products SET stock = stock - 1 WHERE id = ? AND stock > 0",
+            params![winning_slot.product_id],
+        )?;
+        if stock_rows == 0 {
+            return Err(AppError::insufficient_stock("奖品库存不足，无法完成抽奖"));
+        }
+        winning_slot.product_stock -= 1;
+
+        let record_id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        tx.execute(
+            "INSERT INTO purchase_records (id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, ?9, '抽奖')",
+            params![
+                record_id,
+                winning_slot.product_id,
+                winning_slot.product_name,
+                spin_cost,
+                req.student_id,
+                student_name,
+                class_id,
+                created_at.to_rfc3339(),
+                SHIPPING_STATUS_PENDING,
+            ],
+        )?;
+
+        tx.execute(
+            "UPDATE point_transactions SET related_id = ?1 WHERE id = ?2",
+            params![record_id, debit.transaction_id],
+        )?;
+
+        let record = PurchaseRecord {
+            id: record_id,
+            product_id: winning_slot.product_id.clone(),
+            product_name: winning_slot.product_name.clone(),
+            points: spin_cost,
+            student_id: req.student_id.clone(),
+            student_name: student_name.clone(),
+            quantity: 1,
+            class_id: class_id.to_string(),
+            created_at,
+            shipping_status: SHIPPING_STATUS_PENDING.to_string(),
+            source: "抽奖".to_string(),
+            refunded_quantity: 0,
+            shipped_at: None,
+            received_at: None,
+        };
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_PURCHASE_RECORD,
+            entity_id: &record.id,
+            class_id: Some(class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&record),
+        })?;
+
+        tx.commit()?;
+
+        Ok(SpinWheelResult {
+            winning_slot,
+            spent_points: spin_cost,
+            remaining_points,
+            student_id: req.student_id,
+            student_name,
+            record,
+        })
+    }
+
+    /// Moves a record to `status` if the state machine allows it. Setting the status it
+    /// already has is a no-op. Cancelling refunds every unit not refunded yet, giving the
+    /// student's points and the product's stock back.
+    pub fn update_shipping_status(&self, record_id: &str, status: &str) -> Result<(), AppError> {
+        if !SHIPPING_STATUSES.contains(&status) {
+            return Err(AppError::invalid_input(format!("无效的发货状态: {}", status)));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = query_purchase_record(&tx, record_id)?.ok_or_else(|| AppError::not_found("购买记录不存在"))?;
+        if before.shipping_status != status {
+            if status == SHIPPING_STATUS_CANCELLED {
+                check_shipping_transition(&before.shipping_status, status)?;
+                let remaining = before.quantity - before.refunded_quantity;
+                let reason = format!("取消 {} x{}", before.product_name, remaining);
+                refund_record(&tx, &before, remaining, &reason, SHIPPING_STATUS_CANCELLED)?;
+            } else {
+                transition_shipping_status(&tx, &before, status, Utc::now())?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Marks the given records as shipped in one transaction. Records that are already
+    /// shipped are skipped; if any other record cannot be shipped, nothing changes.
+    pub fn ship_purchase_records(&self, record_ids: &[String]) -> Result<BulkShippingResult, AppError> {
+        if record_ids.is_empty() {
+            return Err(AppError::invalid_input("请选择要发货的记录"));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let mut seen = std::collections::HashSet::new();
+        let mut records = Vec::new();
+        let mut skipped = 0;
+        for record_id in record_ids {
+            if !seen.insert(record_id.as_str()) {
+                continue;
+            }
+            let record = query_purchase_record(&tx, record_id)?
+                .ok_or_else(|| AppError::not_found(format!("购买记录不存在: {}", record_id)))?;
+            if record.shipping_status == SHIPPING_STATUS_SHIPPED {
+                skipped += 1;
+            } else {
+                records.push(record);
+            }
+        }
+
+        let updated = ship_records(&tx, &records)?;
+        tx.commit()?;
+
+        Ok(BulkShippingResult { updated, skipped })
+    }
+
+    /// Ships every pending or ready record of one student, across all classes.
+    pub fn ship_student_pending_records(&self, student_id: &str) -> Result<BulkShippingResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        live_student(&tx, student_id)?;
+        let records = collect_rows(
+            &tx,
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE student_id = ?1 AND shipping_status IN (?2, ?3)
+             ORDER BY created_at",
+            params![student_id, SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY],
+            row_to_purchase_record,
+        )?;
+
+        let updated = ship_records(&tx, &records)?;
+        tx.commit()?;
+
+        Ok(BulkShippingResult { updated, skipped: 0 })
+    }
+
+    /// Ships every pending or ready record of a class.
+    pub fn ship_class_pending_records(&self, class_id: &str) -> Result<BulkShippingResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        live_class_name(&tx, class_id)?;
+        let records = collect_rows(
+            &tx,
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE class_id = ?1 AND shipping_status IN (?2, ?3)
+             ORDER BY created_at",
+            params![class_id, SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY],
+            row_to_purchase_record,
+        )?;
+
+        let updated = ship_records(&tx, &records)?;
+        tx.commit()?;
+
+        Ok(BulkShippingResult { updated, skipped: 0 })
+    }
+
+    /// Pick list for one class, or for every class not in the recycle bin when `class_id`
+    /// is `None`.
+    pub fn get_pick_list(&self, class_id: Option<&str>) -> Result<PickList, AppError> {
+        let conn = self.conn.lock().unwrap();
+        query_pick_list(&conn, class_id)
+    }
+
+    /// Reverses `request.quantity` units of a purchase or wheel record (all remaining units
+    /// by default): the student gets back the points paid for them and the product its
+    /// stock. The record is marked `refunded` once nothing is left to refund.
+    pub fn refund_purchase_record(&self, record_id: &str, request: RefundPurchaseRequest) -> Result<RefundPurchaseResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = query_purchase_record(&tx, record_id)?.ok_or_else(|| AppError::not_found("购买记录不存在"))?;
+        let remaining = before.quantity - before.refunded_quantity;
+        if before.shipping_status == SHIPPING_STATUS_REFUNDED || remaining <= 0 {
+            return Err(AppError::conflict("该记录已全部退款，不能重复退款"));
+        }
+
+        let quantity = request.quantity.unwrap_or(remaining);
+        if quantity <= 0 || quantity > remaining {
+            return Err(AppError::invalid_input(format!("退款数量必须在1到{}之间", remaining)));
+        }
+
+        let reason = request
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("退款 {} x{}", before.product_name, quantity));
+        let result = refund_record(&tx, &before, quantity, &reason, SHIPPING_STATUS_REFUNDED)?;
+
+        tx.commit()?;
+        Ok(result)
+    }
+
+    // Semester rollover
+    /// Freezes the class's current standings into an archived term and opens a new one.
+    /// Students and products stay in place; only balances change, each through a ledger row.
+    pub fn rollover_class(&self, class_id: &str, request: RolloverClassRequest) -> Result<ArchivedTerm, AppError> {
+        let term_name = request.term_name.trim();
+        if term_name.is_empty() {
+            return Err(AppError::invalid_input("学期名称不能为空"));
+        }
+
+        let carry_percent = match request.balance_policy.as_str() {
+            ROLLOVER_POLICY_RESET | ROLLOVER_POLICY_CARRY => None,
+            ROLLOVER_POLICY_PERCENT => match request.carry_percent {
+                Some(percent) if (0..=100).contains(&percent) => Some(percent),
+                _ => return Err(AppError::invalid_input("结转比例必须在0到100之间")),
+            },
+            _ => return Err(AppError::invalid_input("无效的积分结转方式")),
+        };
+
+        let mut conn = self.conn.lock().unwrap();
+        // A class in the recycle bin cannot be rolled over
+        live_class_name(&conn, class_id)?;
+        self.auto_backup(&conn, backup::BACKUP_KIND_PRE_ROLLOVER)?;
+        let tx = conn.transaction()?;
+
+        let term_id = Uuid::new_v4().to_string();
+        let archived_at = Utc::now();
+
+        // The new term starts where the previous one was archived
+        let period_start: Option<String> = tx.query_row(
+            "SELECT MAX(archived_at) FROM archived_terms WHERE class_id = ?",
+            [class_id],
+            |row| row.get(0),
+        )?;
+
+        let students: Vec<(String, String, String, i32)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, name, student_number, points FROM students
+                 WHERE class_id = ? AND deleted_at IS NULL
+                 ORDER BY CAST(student_number AS INTEGER) ASC",
+            )?;
+            let student_iter = stmt.query_map([class_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+
+            let mut students = Vec::new();
+            for student in student_iter {
+                students.push(student?);
+            }
+            students
+        };
+
+        let purchase_count: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM purchase_records WHERE class_id = ?1 AND (?2 IS NULL OR created_at > ?2)",
+            params![class_id, period_start],
+            |row| row.get(0),
+        )?;
+
+        let wheel_config = wheel_config_snapshot(&tx, class_id)?;
+        let total_points: i64 = students.iter().map(|(_, _, _, points)| *points as i64).sum();
+
+        tx.execute(
+            &format!("INSERT INTO archived_terms ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", ARCHIVED_TERM_COLUMNS),
+            params![
+                term_id,
+                class_id,
+                term_name,
+                request.balance_policy,
+                carry_percent,
+                students.len() as i32,
+                total_points,
+                purchase_count,
+                wheel_config.as_ref().map(|config| config.to_string()),
+                period_start,
+                archived_at.to_rfc3339()
+            ],
+        )?;
+
+        tx.execute(
+            "INSERT INTO term_purchase_records
+                (term_id, id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at)
+             SELECT ?1, id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE class_id = ?2 AND (?3 IS NULL OR created_at > ?3)",
+            params![term_id, class_id, period_start],
+        )?;
+
+        let reason = format!("学期结转：{}", term_name);
+        for (student_id, student_name, student_number, points) in &students {
+            let carried_points = match carry_percent {
+                Some(percent) => (*points as i64 * percent as i64 / 100) as i32,
+                None if request.balance_policy == ROLLOVER_POLICY_CARRY => *points,
+                None => 0,
+            };
+
+            tx.execute(
+                "INSERT INTO term_student_balances (term_id, student_id, student_name, student_number, final_points, carried_points)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![term_id, student_id, student_name, student_number, points, carried_points],
+            )?;
+
+            let delta = carried_points - points;
+            if delta != 0 {
+                tx.execute(
+                    "UPDATE students SET points = ?1 WHERE id = ?2",
+                    params![carried_points, student_id],
+                )?;
+                record_point_transaction(&tx, PointChange {
+                    student_id,
+                    class_id,
+                    delta,
+                    balance_after: carried_points,
+                    reason: Some(&reason),
+                    source: POINT_SOURCE_ROLLOVER,
+                    related_id: Some(&term_id),
+                    reason_id: None,
+                })?;
+            }
+        }
+
+        let term = tx.query_row(
+            &format!("SELECT {} FROM archived_terms WHERE id = ?", ARCHIVED_TERM_COLUMNS),
+            [&term_id],
+            row_to_archived_term,
+        )?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_ARCHIVED_TERM,
+            entity_id: &term_id,
+            class_id: Some(class_id),
+            action: AUDIT_ACTION_CREATE,
+            before: None,
+            after: to_json(&term),
+        })?;
+
+        tx.commit()?;
+        Ok(term)
+    }
+
+    pub fn get_archived_terms_by_class(&self, class_id: &str) -> Result<Vec<ArchivedTerm>, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM archived_terms WHERE class_id = ? ORDER BY archived_at DESC",
+            ARCHIVED_TERM_COLUMNS
+        ))?;
+
+        let term_iter = stmt.query_map([class_id], row_to_archived_term)?;
+
+        let mut terms = Vec::new();
+        for term in term_iter {
+            terms.push(term?);
+        }
+
+        Ok(terms)
+    }
+
+    pub fn get_archived_term_detail(&self, term_id: &str) -> Result<ArchivedTermDetail, AppError> {
+        let conn = self.conn.lock().unwrap();
+
+        let term = conn
+            .query_row(
+                &format!("SELECT {} FROM archived_terms WHERE id = ?", ARCHIVED_TERM_COLUMNS),
+                [term_id],
+                row_to_archived_term,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("学期存档不存在"))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT student_id, student_name, student_number, final_points, carried_points
+             FROM term_student_balances
+             WHERE term_id = ?
+             ORDER BY final_points DESC, CAST(student_number AS INTEGER) ASC",
+        )?;
+        let balance_iter = stmt.query_map([term_id], |row| {
+            Ok(TermStudentBalance {
+                student_id: row.get(0)?,
+                student_name: row.get(1)?,
+                student_number: row.get(2)?,
+                final_points: row.get(3)?,
+                carried_points: row.get(4)?,
+            })
+        })?;
+
+        let mut balances = Vec::new();
+        for balance in balance_iter {
+            balances.push(balance?);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM term_purchase_records
+             WHERE term_id = ?
+             ORDER BY created_at DESC",
+        )?;
+        let record_iter = stmt.query_map([term_id], row_to_purchase_record)?;
+
+        let mut purchase_records = Vec::new();
+        for record in record_iter {
+            purchase_records.push(record?);
+        }
+
+        Ok(ArchivedTermDetail {
+            term,
+            balances,
+            purchase_records,
+        })
+    }
+
+    // Integrity
+    pub fn check_integrity(&self) -> Result<IntegrityReport, AppError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(run_integrity_checks(&conn)?)
+    }
+
+    /// Fixes every repairable issue in one transaction and returns a fresh report, which
+    /// still lists the issues that need a human decision.
+    pub fn repair_integrity(&self) -> Result<RepairIntegrityResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        self.auto_backup(&conn, backup::BACKUP_KIND_PRE_REPAIR)?;
+        let tx = conn.transaction()?;
+
+        let mut repairs = Vec::new();
+        for check in INTEGRITY_CHECKS {
+            if let Some(repair_sql) = check.repair_sql {
+                let fixed = tx.execute(&repair_sql.replace("{ids}", check.ids_sql), [])?;
+                if fixed > 0 {
+                    repairs.push(IntegrityRepair {
+                        code: check.code.to_string(),
+                        fixed: fixed as i64,
+                    });
+                }
+            }
+        }
+
+        if !repairs.is_empty() {
+            write_audit_log(&tx, AuditEntry {
+                entity_type: AUDIT_ENTITY_DATABASE,
+                entity_id: "integrity",
+                class_id: None,
+                action: AUDIT_ACTION_REPAIR,
+                before: None,
+                after: to_json(&repairs),
+            })?;
+        }
+
+        let report = run_integrity_checks(&tx)?;
+
+        tx.commit()?;
+        Ok(RepairIntegrityResult { repairs, report })
+    }
+
+    // Backups
+    pub fn create_backup(&self) -> Result<BackupInfo, AppError> {
+        let conn = self.conn.lock().unwrap();
+        backup::create_backup(&conn, &self.db_path(), backup::BACKUP_KIND_MANUAL)
+    }
+
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, AppError> {
+        backup::list_backups(&self.db_path())
+    }
+
+    /// Replaces the live database with a backup. The file is validated first and the
+    /// current data is backed up, so a restore can itself be undone.
+    pub fn restore_backup(&self, file_name: &str) -> Result<RestoreBackupResult, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let db_path = self.db_path();
+
+        let backup_path = backup::resolve_backup_path(&db_path, file_name)?;
+        backup::validate_backup(&backup_path)?;
+        let restored = backup::list_backups(&db_path)?
+            .into_iter()
+            .find(|backup| backup.file_name == file_name)
+            .ok_or_else(|| AppError::not_found("备份文件不存在"))?;
+
+        // Rotation waits until the restore has succeeded, otherwise it could delete the
+        // very backup being restored
+        let safety_backup = backup::create_backup(&conn, &db_path, backup::BACKUP_KIND_PRE_RESTORE)?;
+
+        conn.restore(DatabaseName::Main, &backup_path, None::<fn(rusqlite::backup::Progress)>)
+            .map_err(|e| AppError::io(format!("恢复备份失败: {}", e)))?;
+
+        // Older backups are migrated forward before the app touches them
+        migrations::run_migrations(&mut conn, &db_path)?;
+
+        write_audit_log(&conn, AuditEntry {
+            entity_type: AUDIT_ENTITY_DATABASE,
+            entity_id: file_name,
+            class_id: None,
+            action: AUDIT_ACTION_RESTORE,
+            before: None,
+            after: to_json(&restored),
+        })?;
+        self.prune_backups(&conn)?;
+
+        Ok(RestoreBackupResult {
+            restored,
+            safety_backup,
+            schema_info: query_schema_info(&conn)?,
+        })
+    }
+
+    pub fn get_backup_settings(&self) -> Result<BackupSettings, AppError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(BackupSettings {
+            retention_count: get_setting_i32(&conn, SETTING_BACKUP_RETENTION, DEFAULT_BACKUP_RETENTION)?,
+        })
+    }
+
+    pub fn update_backup_settings(&self, settings: BackupSettings) -> Result<BackupSettings, AppError> {
+        if settings.retention_count < 1 {
+            return Err(AppError::invalid_input("备份保留份数必须大于0"));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let before = BackupSettings {
+            retention_count: get_setting_i32(&tx, SETTING_BACKUP_RETENTION, DEFAULT_BACKUP_RETENTION)?,
+        };
+        set_setting(&tx, SETTING_BACKUP_RETENTION, &settings.retention_count.to_string())?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_SETTING,
+            entity_id: SETTING_BACKUP_RETENTION,
+            class_id: None,
+            action: AUDIT_ACTION_UPDATE,
+            before: to_json(&before),
+            after: to_json(&settings),
+        })?;
+
+        tx.commit()?;
+        backup::prune_automatic_backups(&self.db_path(), settings.retention_count as usize)?;
+        Ok(settings)
+    }
+
+    // Data location
+    pub fn get_data_location(&self, config_dir: &Path) -> Result<DataLocation, AppError> {
+        let db_path = self.db_path();
+        let data_dir = db_path.parent().ok_or_else(|| AppError::io("无法确定数据目录"))?;
+
+        Ok(DataLocation {
+            data_dir: data_dir.to_string_lossy().to_string(),
+            db_path: db_path.to_string_lossy().to_string(),
+            settings_path: AppConfig::settings_path(config_dir).to_string_lossy().to_string(),
+            size_bytes: std::fs::metadata(&db_path).map(|metadata| metadata.len()).unwrap_or(0),
+        })
+    }
+
+    /// Moves the database (and its backups) to `target_dir`: copy, verify the copy, save the
+    /// new location, then switch the live connection. The old file is left untouched so the
+    /// move can be checked before it is cleaned up by hand.
+    pub fn move_data_location(&self, config_dir: &Path, target_dir: &str) -> Result<DataLocation, AppError> {
+        let target_dir = PathBuf::from(target_dir.trim());
+        if !target_dir.is_absolute() {
+            return Err(AppError::invalid_input("请选择完整的文件夹路径"));
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let old_path = self.db_path();
+        let old_dir = old_path.parent().ok_or_else(|| AppError::io("无法确定数据目录"))?.to_path_buf();
+        if target_dir == old_dir {
+            return Err(AppError::conflict("数据已在该文件夹中"));
+        }
+
+        std::fs::create_dir_all(&target_dir).map_err(|e| AppError::io(format!("无法创建目标文件夹: {}", e)))?;
+        let new_path = target_dir.join(DB_FILE_NAME);
+        if new_path.exists() {
+            return Err(AppError::conflict("目标文件夹中已存在数据库文件，请选择其他文件夹"));
+        }
+
+        let temp_path = target_dir.join(format!("{}.moving", DB_FILE_NAME));
+        if let Err(e) = copy_and_verify_database(&conn, &temp_path) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        std::fs::rename(&temp_path, &new_path)?;
+
+        // Backups follow the database and are copied before anything switches over, so a
+        // failed copy leaves the app where it was. Existing files at the target are kept.
+        let mut copied_backups = Vec::new();
+        if let Err(e) = copy_backups(&old_dir, &new_path, &mut copied_backups) {
+            for copied in &copied_backups {
+                let _ = std::fs::remove_file(copied);
+            }
+            let _ = std::fs::remove_file(&new_path);
+            return Err(AppError::io(format!("复制备份文件失败: {}", e.message)));
+        }
+
+        let mut config = AppConfig::load(config_dir)?;
+        config.remember_previous_data_dir(&old_dir);
+        config.data_dir = Some(target_dir.clone());
+        if let Err(e) = config.save(config_dir) {
+            for copied in &copied_backups {
+                let _ = std::fs::remove_file(copied);
+            }
+            let _ = std::fs::remove_file(&new_path);
+            return Err(AppError::io(format!("保存数据目录设置失败: {}", e)));
+        }
+
+        *conn = Connection::open(&new_path)?;
+        *self.db_path.write().unwrap() = new_path.clone();
+
+        write_audit_log(&conn, AuditEntry {
+            entity_type: AUDIT_ENTITY_DATABASE,
+            entity_id: "data_location",
+            class_id: None,
+            action: AUDIT_ACTION_UPDATE,
+            before: Some(serde_json::json!({ "dataDir": old_dir.to_string_lossy() })),
+            after: Some(serde_json::json!({ "dataDir": target_dir.to_string_lossy() })),
+        })?;
+        drop(conn);
+
+        self.get_data_location(config_dir)
+    }
+
+    /// Lists databases found in legacy drive-letter folders, the default app data dir and
+    /// folders the data was moved away from, excluding the one in use.
+    pub fn detect_legacy_databases(&self, config_dir: &Path, default_data_dir: &Path) -> Result<Vec<DetectedDatabase>, AppError> {
+        let current_dir = self.db_path().parent().map(Path::to_path_buf);
+        let config = AppConfig::load(config_dir)?;
+
+        let mut candidates: Vec<PathBuf> = LEGACY_DATA_DIRS.iter().map(PathBuf::from).collect();
+        candidates.push(default_data_dir.to_path_buf());
+        candidates.extend(config.previous_data_dirs);
+
+        let mut databases: Vec<DetectedDatabase> = Vec::new();
+        for dir in candidates {
+            if Some(&dir) == current_dir.as_ref() || databases.iter().any(|db| Path::new(&db.data_dir) == dir) {
+                continue;
+            }
+            if let Some(database) = detect_database(&dir) {
+                databases.push(database);
+            }
+        }
+
+        Ok(databases)
+    }
+
+    // Class bundles
+    pub fn export_class_bundle(&self, class_id: &str) -> Result<ClassBundle, AppError> {
+        let conn = self.conn.lock().unwrap();
+
+        let class = conn
+            .query_row(
+                "SELECT id, name, description, student_count, created_at FROM classes WHERE id = ? AND deleted_at IS NULL",
+                [class_id],
+                row_to_class,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("班级不存在"))?;
+
+        let student_sql = |filter: &str| {
+            format!(
+                "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students
+                 WHERE class_id = ?1 AND {}
+                 ORDER BY CAST(student_number AS INTEGER) ASC",
+                filter
+            )
+        };
+        let product_sql = |filter: &str| {
+            format!(
+                "SELECT id, name, points, stock, class_id, created_at FROM products
+                 WHERE class_id = ?1 AND {}
+                 ORDER BY created_at ASC",
+                filter
+            )
+        };
+
+        let students = collect_rows(&conn, &student_sql("deleted_at IS NULL"), [class_id], row_to_student)?;
+        let archived_students = collect_rows(
+            &conn,
+            &student_sql("deleted_at IS NOT NULL AND id IN (SELECT student_id FROM purchase_records WHERE class_id = ?1)"),
+            [class_id],
+            row_to_student,
+        )?;
+        let products = collect_rows(&conn, &product_sql("deleted_at IS NULL"), [class_id], row_to_product)?;
+        let archived_products = collect_rows(
+            &conn,
+            &product_sql("deleted_at IS NOT NULL AND id IN (SELECT product_id FROM purchase_records WHERE class_id = ?1)"),
+            [class_id],
+            row_to_product,
+        )?;
+
+        // Records whose student row is gone entirely cannot be re-linked and are left out
+        let purchase_records = collect_rows(
+            &conn,
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE class_id = ?1 AND student_id IN (SELECT id FROM students WHERE class_id = ?1)
+             ORDER BY created_at ASC",
+            [class_id],
+            row_to_purchase_record,
+        )?;
+
+        let point_reasons = collect_rows(
+            &conn,
+            "SELECT id, class_id, label, default_delta, category, sort_order, created_at
+             FROM point_reasons
+             WHERE class_id = ?
+             ORDER BY sort_order ASC, created_at ASC",
+            [class_id],
+            row_to_point_reason,
+        )?;
+
+        let wheel_config = match conn
+            .query_row("SELECT spin_cost FROM wheel_configs WHERE class_id = ?", [class_id], |row| row.get(0))
+            .optional()?
+        {
+            Some(spin_cost) => Some(BundleWheelConfig {
+                spin_cost,
+                product_ids: collect_rows(
+                    &conn,
+                    "SELECT product_id FROM wheel_slots WHERE class_id = ? ORDER BY slot_index ASC",
+                    [class_id],
+                    |row| row.get(0),
+                )?,
+            }),
+            None => None,
+        };
+
+        Ok(ClassBundle {
+            format: CLASS_BUNDLE_FORMAT.to_string(),
+            version: CLASS_BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            schema_version: migrations::current_version(&conn)?,
+            class,
+            students,
+            archived_students,
+            products,
+            archived_products,
+            purchase_records,
+            point_reasons,
+            wheel_config,
+        })
+    }
+
+    pub fn preview_class_bundle_import(&self, bundle: &ClassBundle) -> Result<ClassBundleImportPreview, AppError> {
+        validate_class_bundle(bundle)?;
+        let conn = self.conn.lock().unwrap();
+
+        let collisions = collect_rows(
+            &conn,
+            "SELECT id, name, description, student_count, created_at FROM classes
+             WHERE deleted_at IS NULL AND (id = ?1 OR name = ?2)
+             ORDER BY created_at ASC",
+            params![bundle.class.id, bundle.class.name.trim()],
+            row_to_class,
+        )?;
+
+        Ok(ClassBundleImportPreview {
+            class_name: bundle.class.name.clone(),
+            students: bundle.students.len() as i32,
+            products: bundle.products.len() as i32,
+            purchase_records: bundle.purchase_records.len() as i32,
+            has_wheel_config: bundle.wheel_config.is_some(),
+            collisions,
+        })
+    }
+
+    /// Imports a bundle either as a brand-new class or merged into an existing one. Every
+    /// id in the bundle is remapped, so the same bundle can be imported any number of times.
+    pub fn import_class_bundle(&self, req: ImportClassBundleRequest) -> Result<ImportClassBundleResult, AppError> {
+        let bundle = &req.bundle;
+        validate_class_bundle(bundle)?;
+
+        let merge = match req.mode.as_str() {
+            BUNDLE_IMPORT_NEW => false,
+            BUNDLE_IMPORT_MERGE => true,
+            _ => return Err(AppError::invalid_input("无效的导入方式")),
+        };
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = Utc::now().to_rfc3339();
+
+        let min_points = get_setting_i32(&tx, SETTING_MIN_POINTS, 0)?;
+        if let Some(student) = bundle.students.iter().chain(&bundle.archived_students).find(|s| s.points < min_points) {
+            return Err(AppError::invalid_input(format!(
+                "班级数据包中的学生 {} 积分 {} 低于最低积分 {}",
+                student.id, student.points, min_points
+            )));
+        }
+
+        let class = if merge {
+            let target_class_id = req.target_class_id.as_deref().ok_or_else(|| AppError::invalid_input("请选择要合并到的班级"))?;
+            tx.query_row(
+                "SELECT id, name, description, student_count, created_at FROM classes WHERE id = ? AND deleted_at IS NULL",
+                [target_class_id],
+                row_to_class,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found("目标班级不存在"))?
+        } else {
+            let name_taken: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM classes WHERE name = ? AND deleted_at IS NULL)",
+                [bundle.class.name.trim()],
+                |row| row.get(0),
+            )?;
+            let name = if name_taken {
+                format!("{}（导入）", bundle.class.name.trim())
+            } else {
+                bundle.class.name.trim().to_string()
+            };
+
+            let id = Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO classes (id, name, description, student_count, created_at) VALUES (?1, ?2, ?3, 0, ?4)",
+                params![id, name, bundle.class.description, now],
+            )?;
+            query_class(&tx, &id)?.ok_or_else(|| AppError::not_found("班级不存在"))?
+        };
+
+        let mut result = ImportClassBundleResult {
+            class: class.clone(),
+            students_created: 0,
+            students_merged: 0,
+            products_created: 0,
+            products_merged: 0,
+            purchase_records_imported: 0,
+            purchase_records_skipped: 0,
+            wheel_config_imported: false,
+        };
+
+        let reason = format!("导入班级数据：{}", bundle.class.name);
+
+        let mut student_ids = std::collections::HashMap::new();
+        let bundled_students = bundle.students.iter().map(|s| (s, false)).chain(bundle.archived_students.iter().map(|s| (s, true)));
+        for (student, archived) in bundled_students {
+            if merge {
+                if let Some(existing_id) = find_merge_student(&tx, &class.id, student)? {
+                    student_ids.insert(student.id.clone(), existing_id);
+                    result.students_merged += 1;
+                    continue;
+                }
+            }
+
+            let id = Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO students (id, name, student_number, points, class_id, class_name, created_at, deleted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    id,
+                    student.name,
+                    student.student_number,
+                    student.points,
+                    class.id,
+                    class.name,
+                    student.created_at.to_rfc3339(),
+                    archived.then_some(&now)
+                ],
+            )?;
+
+            if student.points != 0 {
+                record_point_transaction(&tx, PointChange {
+                    student_id: &id,
+                    class_id: &class.id,
+                    delta: student.points,
+                    balance_after: student.points,
+                    reason: Some(&reason),
+                    source: POINT_SOURCE_IMPORT,
+                    related_id: None,
+                    reason_id: None,
+                })?;
+            }
+
+            student_ids.insert(student.id.clone(), id);
+            result.students_created += 1;
+        }
+
+        let mut product_ids = std::collections::HashMap::new();
+        let bundled_products = bundle.products.iter().map(|p| (p, false)).chain(bundle.archived_products.iter().map(|p| (p, true)));
+        for (product, archived) in bundled_products {
+            if merge {
+                let existing_id: Option<String> = tx
+                    .query_row(
+                        "SELECT id FROM products WHERE class_id = ? AND name = ? ORDER BY deleted_at IS NOT NULL LIMIT 1",
+                        params![class.id, product.name],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                if let Some(existing_id) = existing_id {
+                    product_ids.insert(product.id.clone(), existing_id);
+                    result.products_merged += 1;
+                    continue;
+                }
+            }
+
+            let id = Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO products (id, name, points, stock, class_id, created_at, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    product.name,
+                    product.points,
+                    product.stock,
+                    class.id,
+                    product.created_at.to_rfc3339(),
+                    archived.then_some(&now)
+                ],
+            )?;
+
+            product_ids.insert(product.id.clone(), id);
+            result.products_created += 1;
+        }
+
+        for record in &bundle.purchase_records {
+            let (Some(student_id), Some(product_id)) = (student_ids.get(&record.student_id), product_ids.get(&record.product_id)) else {
+                result.purchase_records_skipped += 1;
+                continue;
+            };
+
+            // Re-importing into the same class must not duplicate orders
+            if merge {
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS(
+                        SELECT 1 FROM purchase_records
+                        WHERE class_id = ?1 AND student_id = ?2 AND product_id = ?3 AND created_at = ?4 AND quantity = ?5
+                    )",
+                    params![class.id, student_id, product_id, record.created_at.to_rfc3339(), record.quantity],
+                    |row| row.get(0),
+                )?;
+                if exists {
+                    result.purchase_records_skipped += 1;
+                    continue;
+                }
+            }
+
+            tx.execute(
+                "INSERT INTO purchase_records (id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    product_id,
+                    record.product_name,
+                    record.points,
+                    student_id,
+                    record.student_name,
+                    record.quantity,
+                    class.id,
+                    record.created_at.to_rfc3339(),
+                    normalize_shipping_status(&record.shipping_status),
+                    record.source,
+                    record.refunded_quantity,
+                    record.shipped_at.map(|time| time.to_rfc3339()),
+                    record.received_at.map(|time| time.to_rfc3339())
+                ],
+            )?;
+            result.purchase_records_imported += 1;
+        }
+
+        for reason in &bundle.point_reasons {
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM point_reasons WHERE class_id = ? AND label = ?)",
+                params![class.id, reason.label],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                tx.execute(
+                    "INSERT INTO point_reasons (id, class_id, label, default_delta, category, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        Uuid::new_v4().to_string(),
+                        class.id,
+                        reason.label,
+                        reason.default_delta,
+                        reason.category,
+                        reason.sort_order,
+                        reason.created_at.to_rfc3339()
+                    ],
+                )?;
+            }
+        }
+
+        // A merge never replaces a wheel the target class already has
+        if let Some(wheel_config) = &bundle.wheel_config {
+            let has_wheel: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM wheel_configs WHERE class_id = ?)",
+                [&class.id],
+                |row| row.get(0),
+            )?;
+
+            if !has_wheel {
+                tx.execute(
+                    "INSERT INTO wheel_configs (class_id, spin_cost, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![class.id, wheel_config.spin_cost, now, now],
+                )?;
+
+                let slot_products = wheel_config
+                    .product_ids
+                    .iter()
+                    .filter_map(|product_id| product_ids.get(product_id));
+                for (slot_index, product_id) in slot_products.enumerate() {
+                    tx.execute(
+                        "INSERT INTO wheel_slots (id, class_id, product_id, slot_index, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![Uuid::new_v4().to_string(), class.id, product_id, slot_index as i32, now],
+                    )?;
+                }
+                result.wheel_config_imported = true;
+            }
+        }
+
+        refresh_student_count(&tx, &class.id)?;
+        result.class = query_class(&tx, &class.id)?.ok_or_else(|| AppError::not_found("班级不存在"))?;
+
+        write_audit_log(&tx, AuditEntry {
+            entity_type: AUDIT_ENTITY_CLASS,
+            entity_id: &class.id,
+            class_id: Some(&class.id),
+            action: AUDIT_ACTION_IMPORT,
+            before: None,
+            after: to_json(&result),
+        })?;
+
+        tx.commit()?;
+        Ok(result)
+    }
+
+    // Exports
+    /// Student roster with competition ranking by points (ties share a rank).
+    pub fn export_students(&self, class_id: &str, format: &str) -> Result<ExportFile, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let class_name = live_class_name(&conn, class_id)?;
+
+        let students = collect_rows(
+            &conn,
+            "SELECT id, name, student_number, points, class_id, class_name, created_at FROM students
+             WHERE class_id = ? AND deleted_at IS NULL
+             ORDER BY points DESC, CAST(student_number AS INTEGER) ASC",
+            [class_id],
+            row_to_student,
+        )?;
+
+        let mut rows = Vec::with_capacity(students.len());
+        let mut rank = 0;
+        for (index, student) in students.iter().enumerate() {
+            if index == 0 || students[index - 1].points != student.points {
+                rank = index as i32 + 1;
+            }
+            rows.push(vec![
+                ExportCell::from(rank),
+                ExportCell::from(student.student_number.as_str()),
+                ExportCell::from(student.name.as_str()),
+                ExportCell::from(student.points),
+            ]);
+        }
+
+        ExportTable {
+            sheet_name: "学生名单",
+            headers: export::STUDENT_EXPORT_HEADERS,
+            rows,
+        }
+        .into_file(&format!("{}_学生名单", class_name), format)
+    }
+
+    pub fn export_products(&self, class_id: &str, format: &str) -> Result<ExportFile, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let class_name = live_class_name(&conn, class_id)?;
+
+        let products = collect_rows(
+            &conn,
+            "SELECT id, name, points, stock, class_id, created_at FROM products
+             WHERE class_id = ? AND deleted_at IS NULL
+             ORDER BY created_at ASC",
+            [class_id],
+            row_to_product,
+        )?;
+
+        let rows = products
+            .iter()
+            .map(|product| vec![
+                ExportCell::from(product.name.as_str()),
+                ExportCell::from(product.points),
+                ExportCell::from(product.stock),
+                ExportCell::from(format_local_time(&product.created_at)),
+            ])
+            .collect();
+
+        ExportTable {
+            sheet_name: "商品库存",
+            headers: export::PRODUCT_EXPORT_HEADERS,
+            rows,
+        }
+        .into_file(&format!("{}_商品库存", class_name), format)
+    }
+
+    /// Purchase records filtered by local date range, source and shipping status, streamed
+    /// straight from the database so large histories never pass through the webview.
+    pub fn export_purchase_records(&self, class_id: &str, filter: &ExportPurchaseRecordsRequest, format: &str) -> Result<ExportFile, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let class_name = live_class_name(&conn, class_id)?;
+
+        let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty() && *value != "all").map(str::to_string);
+        let start = non_empty(&filter.start_date).map(|date| local_date_start(&date, 0)).transpose()?;
+        let end = non_empty(&filter.end_date).map(|date| local_date_start(&date, 1)).transpose()?;
+        let source = non_empty(&filter.source).unwrap_or_default();
+        let shipping_status = non_empty(&filter.shipping_status).unwrap_or_default();
+
+        let records = collect_rows(
+            &conn,
+            "SELECT id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source, refunded_quantity, shipped_at, received_at
+             FROM purchase_records
+             WHERE class_id = ?1
+               AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))
+               AND (?3 IS NULL OR julianday(created_at) < julianday(?3))
+               AND (?4 = '' OR source = ?4)
+               AND (?5 = '' OR shipping_status = ?5)
+             ORDER BY created_at DESC",
+            params![class_id, start, end, source, shipping_status],
+            row_to_purchase_record,
+        )?;
+
+        let rows = records
+            .iter()
+            .map(|record| vec![
+                ExportCell::from(format_local_time(&record.created_at)),
+                ExportCell::from(record.student_name.as_str()),
+                ExportCell::from(record.product_name.as_str()),
+                ExportCell::from(record.quantity),
+                ExportCell::from(record.points),
+                ExportCell::from(record.source.as_str()),
+                ExportCell::from(export::shipping_status_label(&record.shipping_status)),
+                ExportCell::from(record.shipped_at.as_ref().map(format_local_time).unwrap_or_default()),
+                ExportCell::from(record.received_at.as_ref().map(format_local_time).unwrap_or_default()),
+            ])
+            .collect();
+
+        ExportTable {
+            sheet_name: "购买记录",
+            headers: export::PURCHASE_EXPORT_HEADERS,
+            rows,
+        }
+        .into_file(&format!("{}_购买记录", class_name), format)
+    }
 
-        Ok(WheelConfig {
-            class_id: class_id.to_string(),
-            spin_cost,
-            slots,
-            created_at: DateTime::parse_from_rfc3339(&created_at_raw)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: DateTime::parse_from_rfc3339(&updated_at_raw)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now()),
-        })
+    pub fn export_pick_list(&self, class_id: Option<&str>, format: &str) -> Result<ExportFile, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let title = match class_id {
+            Some(class_id) => format!("{}_备货清单", live_class_name(&conn, class_id)?),
+            None => "全部班级_备货清单".to_string(),
+        };
+        let pick_list = query_pick_list(&conn, class_id)?;
+
+        // A total row per product, followed by one row per student with a blank box to tick
+        let mut rows = Vec::new();
+        for product in &pick_list.products {
+            rows.push(vec![
+                ExportCell::from(product.class_name.as_str()),
+                ExportCell::from(product.product_name.as_str()),
+                ExportCell::from("合计"),
+                ExportCell::from(""),
+                ExportCell::from(product.total_quantity),
+                ExportCell::from(""),
+            ]);
+            for student in &product.students {
+                rows.push(vec![
+                    ExportCell::from(""),
+                    ExportCell::from(""),
+                    ExportCell::from(student.student_name.as_str()),
+                    ExportCell::from(student.student_number.as_deref().unwrap_or_default()),
+                    ExportCell::from(student.quantity),
+                    ExportCell::from(""),
+                ]);
+            }
+        }
+
+        ExportTable {
+            sheet_name: "备货清单",
+            headers: export::PICK_LIST_EXPORT_HEADERS,
+            rows,
+        }
+        .into_file(&title, format)
     }
 
-    pub fn save_wheel_config(
+    // Audit log
+    pub fn get_audit_logs_paginated(
         &self,
-        class_id: &str,
-        req: SaveWheelConfigRequest,
-    ) -> Result<WheelConfig, Box<dyn std::error::Error>> {
-        if req.spin_cost <= 0 {
-            return Err("抽奖消耗积分必须大于0".into());
+        page: i64,
+        page_size: i64,
+        class_id: Option<&str>,
+        entity_type: Option<&str>,
+        entity_id: Option<&str>,
+    ) -> Result<PaginatedAuditLogs, AppError> {
+        validate_page(page, page_size)?;
+        let conn = self.conn.lock().unwrap();
+
+        let class_value = class_id.filter(|value| !value.trim().is_empty()).unwrap_or("");
+        let entity_type_value = entity_type.filter(|value| !value.trim().is_empty() && *value != "all").unwrap_or("");
+        let entity_id_value = entity_id.filter(|value| !value.trim().is_empty()).unwrap_or("");
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM audit_logs
+             WHERE (?1 = '' OR class_id = ?1) AND (?2 = '' OR entity_type = ?2) AND (?3 = '' OR entity_id = ?3)",
+            params![class_value, entity_type_value, entity_id_value],
+            |row| row.get(0),
+        )?;
+
+        let total_pages = (total + page_size - 1) / page_size;
+        let offset = (page - 1) * page_size;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, entity_type, entity_id, class_id, action, before_json, after_json, created_at
+             FROM audit_logs
+             WHERE (?1 = '' OR class_id = ?1) AND (?2 = '' OR entity_type = ?2) AND (?3 = '' OR entity_id = ?3)
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ?4 OFFSET ?5",
+        )?;
+
+        let log_iter = stmt.query_map(
+            params![class_value, entity_type_value, entity_id_value, page_size, offset],
+            row_to_audit_log,
+        )?;
+
+        let mut logs = Vec::new();
+        for log in log_iter {
+            logs.push(log?);
         }
-        if req.product_ids.is_empty() {
-            return Err("转盘至少需要一个奖品格子".into());
+
+        Ok(PaginatedAuditLogs {
+            logs,
+            total,
+            total_pages,
+            current_page: page,
+            page_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    /// A database in a fresh temp dir, removed again when the test drops it.
+    struct TestDatabase {
+        dir: PathBuf,
+        db: Database,
+    }
+
+    impl TestDatabase {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("pointhub-database-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let db = Database::open(&dir.join(DB_FILE_NAME)).unwrap();
+            TestDatabase { dir, db }
         }
+    }
 
-        let mut conn = self.conn.lock().unwrap();
-        let tx = conn.transaction()?;
+    impl std::ops::Deref for TestDatabase {
+        type Target = Database;
 
-        let class_exists: Option<String> = tx
-            .query_row(
-                "SELECT id FROM classes WHERE id = ?",
-                [class_id],
-                |row| row.get(0),
-            )
-            .optional()?;
-        if class_exists.is_none() {
-            return Err("班级不存在".into());
+        fn deref(&self) -> &Database {
+            &self.db
         }
+    }
 
-        for product_id in &req.product_ids {
-            let product_exists: Option<String> = tx
-                .query_row(
-                    "SELECT id FROM products WHERE id = ? AND class_id = ?",
-                    params![product_id, class_id],
-                    |row| row.get(0),
-                )
-                .optional()?;
-            if product_exists.is_none() {
-                return Err(format!("奖品不存在或不属于当前班级: {}", product_id).into());
-            }
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
         }
+    }
 
-        let now = Utc::now().to_rfc3339();
-        tx.execute(
-            "INSERT INTO wheel_configs (class_id, spin_cost, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4)
-             ON CONFLICT(class_id) DO UPDATE SET
-                spin_cost = excluded.spin_cost,
-                updated_at = excluded.updated_at",
-            params![class_id, req.spin_cost, now, now],
-        )?;
+    fn class(db: &Database, name: &str) -> Class {
+        db.create_class(CreateClassRequest { name: name.to_string(), description: None }).unwrap()
+    }
 
-        tx.execute("DELETE FROM wheel_slots WHERE class_id = ?", [class_id])?;
+    fn student(db: &Database, class_id: &str, number: &str, points: i32) -> Student {
+        db.create_student(CreateStudentRequest {
+            name: format!("学生{}", number),
+            student_number: number.to_string(),
+            points,
+            class_id: class_id.to_string(),
+        })
+        .unwrap()
+    }
 
-        for (index, product_id) in req.product_ids.iter().enumerate() {
-            tx.execute(
-                "INSERT INTO wheel_slots (id, class_id, product_id, slot_index, created_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![
-                    Uuid::new_v4().to_string(),
-                    class_id,
-                    product_id,
-                    index as i32,
-                    now
-                ],
-            )?;
+    fn product(db: &Database, class_id: &str, name: &str, points: i32, stock: i32) -> Product {
+        db.create_product(CreateProductRequest {
+            name: name.to_string(),
+            points,
+            stock,
+            class_id: class_id.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn points_of(db: &Database, student_id: &str) -> i32 {
+        let conn = db.conn.lock().unwrap();
+        query_student(&conn, student_id).unwrap().unwrap().points
+    }
+
+    /// A class with two students, a product, a reason, a wheel and a purchase, exported as a bundle.
+    fn sample_bundle(db: &Database) -> ClassBundle {
+        let class = class(db, "一班");
+        let first = student(db, &class.id, "1", 30);
+        student(db, &class.id, "2", 0);
+        let pencil = product(db, &class.id, "铅笔", 5, 10);
+        db.create_point_reason(CreatePointReasonRequest {
+            class_id: class.id.clone(),
+            label: "按时交作业".to_string(),
+            default_delta: 2,
+            category: None,
+            sort_order: None,
+        })
+        .unwrap();
+        db.save_wheel_config(&class.id, SaveWheelConfigRequest { spin_cost: 10, product_ids: vec![pencil.id.clone()] }).unwrap();
+        db.create_purchase_record(CreatePurchaseRequest { product_id: pencil.id, student_id: first.id, quantity: 2 }).unwrap();
+        db.export_class_bundle(&class.id).unwrap()
+    }
+
+    fn purchase(db: &Database, product_id: &str, student_id: &str, quantity: i32) -> Result<PurchaseRecord, AppError> {
+        db.create_purchase_record(CreatePurchaseRequest {
+            product_id: product_id.to_string(),
+            student_id: student_id.to_string(),
+            quantity,
+        })
+    }
+
+    #[test]
+    fn purchases_reject_non_positive_quantities_and_respect_the_floor() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 12);
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+
+        for quantity in [0, -2] {
+            assert_eq!(purchase(&db, &pencil.id, &student.id, quantity).unwrap_err().code, ErrorCode::InvalidInput);
         }
 
-        tx.commit()?;
-        drop(conn);
+        // A total that overflows i32 is rejected instead of panicking with the connection locked
+        let trophy = product(&db, &class.id, "奖杯", 1_000_000, 1_000_000);
+        assert_eq!(purchase(&db, &trophy.id, &student.id, 5000).unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(points_of(&db, &student.id), 12);
 
-        self.get_wheel_config(class_id)
+        db.update_point_settings(PointSettings { min_points: 5 }).unwrap();
+        let err = purchase(&db, &pencil.id, &student.id, 2).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InsufficientPoints);
+
+        let record = purchase(&db, &pencil.id, &student.id, 1).unwrap();
+        assert_eq!(points_of(&db, &student.id), 7);
+
+        let ledger = db.get_student_point_transactions(&student.id).unwrap();
+        let debit = ledger.iter().find(|row| row.source == POINT_SOURCE_PURCHASE).unwrap();
+        assert_eq!((debit.delta, debit.balance_after), (-5, 7));
+        assert_eq!(debit.related_id.as_deref(), Some(record.id.as_str()));
     }
 
-    pub fn spin_wheel(
-        &self,
-        class_id: &str,
-        req: SpinWheelRequest,
-    ) -> Result<SpinWheelResult, Box<dyn std::error::Error>> {
-        let mut conn = self.conn.lock().unwrap();
-        let tx = conn.transaction()?;
+    #[test]
+    fn undo_is_refused_once_the_award_has_been_spent_below_the_floor() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 0);
+        let pencil = product(&db, &class.id, "铅笔", 8, 10);
 
-        let spin_cost: i32 = tx
-            .query_row(
-                "SELECT spin_cost FROM wheel_configs WHERE class_id = ?",
-                [class_id],
-                |row| row.get(0),
-            )
-            .optional()?
-            .ok_or("请先配置大转盘")?;
+        let award = db.adjust_student_points(&student.id, 10, Some("竞赛获奖"), None).unwrap();
+        purchase(&db, &pencil.id, &student.id, 1).unwrap();
 
-        if spin_cost <= 0 {
-            return Err("抽奖消耗积分配置无效".into());
+        let err = db.undo_point_operation(&award.transaction_id).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InsufficientPoints);
+        assert_eq!(points_of(&db, &student.id), 2);
+
+        // Nothing was written, so the award can still be undone once the floor allows it
+        db.update_point_settings(PointSettings { min_points: -10 }).unwrap();
+        db.undo_point_operation(&award.transaction_id).unwrap();
+        assert_eq!(points_of(&db, &student.id), -8);
+    }
+
+    #[test]
+    fn refunds_the_rest_of_a_partially_refunded_record() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 30);
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+        let record = purchase(&db, &pencil.id, &student.id, 3).unwrap();
+
+        let partial = db
+            .refund_purchase_record(&record.id, RefundPurchaseRequest { quantity: Some(1), reason: None })
+            .unwrap();
+        assert_eq!((partial.refunded_quantity, partial.refunded_points), (1, 5));
+        assert_eq!(partial.record.shipping_status, SHIPPING_STATUS_PENDING);
+
+        let err = db
+            .refund_purchase_record(&record.id, RefundPurchaseRequest { quantity: Some(3), reason: None })
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+
+        let rest = db
+            .refund_purchase_record(&record.id, RefundPurchaseRequest { quantity: None, reason: None })
+            .unwrap();
+        assert_eq!((rest.refunded_quantity, rest.refunded_points, rest.student_points), (2, 10, 30));
+        assert_eq!(rest.record.refunded_quantity, 3);
+        assert_eq!(rest.record.shipping_status, SHIPPING_STATUS_REFUNDED);
+        assert_eq!(db.get_products_by_class(&class.id).unwrap()[0].stock, 10);
+
+        let err = db
+            .refund_purchase_record(&record.id, RefundPurchaseRequest { quantity: None, reason: None })
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+    }
+
+    #[test]
+    fn rejects_illegal_shipping_transitions() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 30);
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+        let record = purchase(&db, &pencil.id, &student.id, 1).unwrap();
+
+        let status_of = |id: &str| {
+            let conn = db.conn.lock().unwrap();
+            query_purchase_record(&conn, id).unwrap().unwrap().shipping_status
+        };
+
+        // Pending cannot skip straight to received
+        let err = db.update_shipping_status(&record.id, SHIPPING_STATUS_RECEIVED).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert_eq!(status_of(&record.id), SHIPPING_STATUS_PENDING);
+
+        db.update_shipping_status(&record.id, SHIPPING_STATUS_SHIPPED).unwrap();
+        for status in [SHIPPING_STATUS_PENDING, SHIPPING_STATUS_READY, SHIPPING_STATUS_CANCELLED] {
+            let err = db.update_shipping_status(&record.id, status).unwrap_err();
+            assert_eq!(err.code, ErrorCode::Conflict);
         }
 
-        let mut slots_stmt = tx.prepare(
-            "SELECT
-                ws.id,
-                ws.class_id,
-                ws.product_id,
-                p.name,
-                p.points,
-                p.stock,
-                ws.slot_index
-             FROM wheel_slots ws
-             JOIN products p ON p.id = ws.product_id
-             WHERE ws.class_id = ?
-             ORDER BY ws.slot_index ASC",
-        )?;
+        db.update_shipping_status(&record.id, SHIPPING_STATUS_RECEIVED).unwrap();
+        let err = db.update_shipping_status(&record.id, SHIPPING_STATUS_SHIPPED).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert_eq!(status_of(&record.id), SHIPPING_STATUS_RECEIVED);
 
-        let slot_rows = slots_stmt.query_map([class_id], |row| {
-            Ok(WheelSlot {
-                id: row.get(0)?,
-                class_id: row.get(1)?,
-                product_id: row.get(2)?,
-                product_name: row.get(3)?,
-                product_points: row.get(4)?,
-                product_stock: row.get(5)?,
-                slot_index: row.get(6)?,
+        let err = db.update_shipping_status(&record.id, SHIPPING_STATUS_REFUNDED).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn bulk_shipping_ignores_students_in_the_recycle_bin() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 30);
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+        purchase(&db, &pencil.id, &student.id, 1).unwrap();
+
+        db.delete_student(&student.id).unwrap();
+        let err = db.ship_student_pending_records(&student.id).unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+
+        db.restore_student(&student.id).unwrap();
+        assert_eq!(db.ship_student_pending_records(&student.id).unwrap().updated, 1);
+    }
+
+    #[test]
+    fn rollover_carry_percent_rounds_toward_zero() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        db.update_point_settings(PointSettings { min_points: -100 }).unwrap();
+        let odd = student(&db, &class.id, "1", 33);
+        let negative = student(&db, &class.id, "2", -7);
+        let zero = student(&db, &class.id, "3", 0);
+
+        let term = db
+            .rollover_class(&class.id, RolloverClassRequest {
+                term_name: "2024 秋季".to_string(),
+                balance_policy: ROLLOVER_POLICY_PERCENT.to_string(),
+                carry_percent: Some(50),
             })
-        })?;
+            .unwrap();
+
+        assert_eq!(points_of(&db, &odd.id), 16);
+        assert_eq!(points_of(&db, &negative.id), -3);
+        assert_eq!(points_of(&db, &zero.id), 0);
+
+        let detail = db.get_archived_term_detail(&term.id).unwrap();
+        let carried: Vec<(i32, i32)> = detail.balances.iter().map(|b| (b.final_points, b.carried_points)).collect();
+        assert_eq!(carried, vec![(33, 16), (0, 0), (-7, -3)]);
+        assert!(db.check_integrity().unwrap().healthy);
+
+        let err = db
+            .rollover_class(&class.id, RolloverClassRequest {
+                term_name: "2025 春季".to_string(),
+                balance_policy: ROLLOVER_POLICY_PERCENT.to_string(),
+                carry_percent: Some(101),
+            })
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+    }
 
-        let mut slots = Vec::new();
-        for slot in slot_rows {
-            slots.push(slot?);
-        }
-        drop(slots_stmt);
+    #[test]
+    fn bundle_import_round_trips() {
+        let db = TestDatabase::new();
+        let bundle = sample_bundle(&db);
 
-        if slots.is_empty() {
-            return Err("转盘未配置奖品格子".into());
-        }
+        let imported = db
+            .import_class_bundle(ImportClassBundleRequest {
+                bundle: bundle.clone(),
+                mode: BUNDLE_IMPORT_NEW.to_string(),
+                target_class_id: None,
+            })
+            .unwrap();
+        assert_eq!(imported.class.name, "一班（导入）");
+        assert_eq!((imported.students_created, imported.products_created, imported.purchase_records_imported), (2, 1, 1));
+        assert!(imported.wheel_config_imported);
+
+        let copy = db.export_class_bundle(&imported.class.id).unwrap();
+        let students = |bundle: &ClassBundle| -> Vec<(String, String, i32)> {
+            bundle.students.iter().map(|s| (s.name.clone(), s.student_number.clone(), s.points)).collect()
+        };
+        let products = |bundle: &ClassBundle| -> Vec<(String, i32, i32)> {
+            bundle.products.iter().map(|p| (p.name.clone(), p.points, p.stock)).collect()
+        };
+        assert_eq!(students(&copy), students(&bundle));
+        assert_eq!(products(&copy), products(&bundle));
+        assert_eq!(copy.point_reasons.len(), 1);
+        assert_eq!(copy.point_reasons[0].label, bundle.point_reasons[0].label);
+        assert_eq!(copy.purchase_records.len(), 1);
+        assert_eq!(copy.purchase_records[0].student_id, copy.students[0].id);
+        assert_eq!(copy.purchase_records[0].quantity, 2);
+
+        // Every id is remapped, and the wheel follows the product to its new id
+        assert_ne!(copy.products[0].id, bundle.products[0].id);
+        let wheel = copy.wheel_config.unwrap();
+        assert_eq!(wheel.spin_cost, 10);
+        assert_eq!(wheel.product_ids, vec![copy.products[0].id.clone()]);
+        assert!(db.check_integrity().unwrap().healthy);
+    }
 
-        if let Some(slot) = slots.iter().find(|slot| slot.product_stock <= 0) {
-            return Err(format!("奖品库存不足，无法开启转盘: {}", slot.product_name).into());
-        }
+    #[test]
+    fn undoing_one_batch_row_undoes_the_whole_batch() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let first = student(&db, &class.id, "1", 0);
+        let second = student(&db, &class.id, "2", 0);
+
+        let batch = db
+            .batch_adjust_points(BatchAdjustPointsRequest {
+                student_ids: None,
+                class_id: Some(class.id.clone()),
+                delta: 5,
+                reason: Some("大扫除".to_string()),
+                reason_id: None,
+            })
+            .unwrap();
 
-        let (student_name, current_points): (String, i32) = tx
-            .query_row(
-                "SELECT name, points FROM students WHERE id = ? AND class_id = ?",
-                params![req.student_id, class_id],
-                |row| Ok((row.get(0)?, row.get(1)?)),
-            )
-            .optional()?
-            .ok_or("学生不存在或不属于当前班级")?;
+        let reversals = db.undo_point_operation(&batch.results[0].transaction_id).unwrap();
+        assert_eq!(reversals.len(), 2);
+        assert_eq!(points_of(&db, &first.id), 0);
+        assert_eq!(points_of(&db, &second.id), 0);
 
-        if current_points < spin_cost {
-            return Err("积分不足，无法开启转盘".into());
+        let err = db.undo_point_operation(&batch.results[1].transaction_id).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+    }
+
+    #[test]
+    fn purchase_record_paging_rejects_empty_pages() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+
+        for (page, page_size) in [(1, 0), (0, 20), (-1, 20)] {
+            let err = db.get_purchase_records_paginated(&class.id, page, page_size, None).unwrap_err();
+            assert_eq!(err.code, ErrorCode::InvalidInput);
         }
+        assert_eq!(db.get_purchase_records_paginated(&class.id, 1, 20, None).unwrap().total_pages, 0);
+    }
 
-        let winning_index = (Uuid::new_v4().as_u128() % slots.len() as u128) as usize;
-        let mut winning_slot = slots[winning_index].clone();
+    #[test]
+    fn deleting_a_reason_unlinks_its_ledger_rows() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 0);
+        let reason = db
+            .create_point_reason(CreatePointReasonRequest {
+                class_id: class.id.clone(),
+                label: "按时交作业".to_string(),
+                default_delta: 2,
+                category: None,
+                sort_order: None,
+            })
+            .unwrap();
+        db.adjust_student_points(&student.id, 2, None, Some(&reason.id)).unwrap();
 
-        tx.execute(
-            "UPDATE students SET points = points - ? WHERE id = ?",
-            params![spin_cost, req.student_id],
-        )?;
+        db.delete_point_reason(&reason.id).unwrap();
 
-        let stock_rows = tx.execute(
-            "UPDATE products SET stock = stock - 1 WHERE id = ? AND stock > 0",
-            params![winning_slot.product_id],
-        )?;
-        if stock_rows == 0 {
-            return Err("奖品库存不足，无法完成抽奖".into());
-        }
-        winning_slot.product_stock -= 1;
+        let ledger = db.get_student_point_transactions(&student.id).unwrap();
+        assert_eq!(ledger.len(), 1);
+        assert_eq!(ledger[0].reason_id, None);
+        assert_eq!(ledger[0].reason.as_deref(), Some("按时交作业"));
+        assert!(db.check_integrity().unwrap().healthy);
+    }
 
-        let record_id = Uuid::new_v4().to_string();
-        let created_at = Utc::now();
-        tx.execute(
-            "INSERT INTO purchase_records (id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, 'pending', '抽奖')",
-            params![
-                record_id,
-                winning_slot.product_id,
-                winning_slot.product_name,
-                spin_cost,
-                req.student_id,
-                student_name,
-                class_id,
-                created_at.to_rfc3339(),
-            ],
-        )?;
+    #[test]
+    fn repair_keeps_the_wheel_of_a_class_in_the_recycle_bin() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+        let eraser = product(&db, &class.id, "橡皮", 3, 10);
+        db.save_wheel_config(&class.id, SaveWheelConfigRequest { spin_cost: 10, product_ids: vec![pencil.id, eraser.id] }).unwrap();
 
-        let remaining_points: i32 = tx.query_row(
-            "SELECT points FROM students WHERE id = ?",
-            [req.student_id.as_str()],
-            |row| row.get(0),
-        )?;
+        db.delete_class(&class.id).unwrap();
+        assert!(db.check_integrity().unwrap().healthy);
+        assert!(db.repair_integrity().unwrap().repairs.is_empty());
 
-        tx.commit()?;
+        db.restore_class(&class.id).unwrap();
+        assert_eq!(db.get_wheel_config(&class.id).unwrap().slots.len(), 2);
+    }
 
-        let record = PurchaseRecord {
-            id: record_id,
-            product_id: winning_slot.product_id.clone(),
-            product_name: winning_slot.product_name.clone(),
-            points: spin_cost,
-            student_id: req.student_id.clone(),
-            student_name: student_name.clone(),
-            quantity: 1,
-            class_id: class_id.to_string(),
-            created_at,
-            shipping_status: "pending".to_string(),
-            source: "抽奖".to_string(),
-        };
+    #[test]
+    fn purge_keeps_products_with_purchase_history() {
+        let db = TestDatabase::new();
+        let class = class(&db, "一班");
+        let student = student(&db, &class.id, "1", 30);
+        let pencil = product(&db, &class.id, "铅笔", 5, 10);
+        let eraser = product(&db, &class.id, "橡皮", 3, 10);
+        let record = purchase(&db, &pencil.id, &student.id, 1).unwrap();
+        db.update_shipping_status(&record.id, SHIPPING_STATUS_SHIPPED).unwrap();
+
+        db.delete_product(&pencil.id, true).unwrap();
+        db.delete_product(&eraser.id, false).unwrap();
+        assert_eq!(db.purge_trash(None).unwrap().products, 1);
+
+        let conn = db.conn.lock().unwrap();
+        assert!(query_product(&conn, &pencil.id).unwrap().is_some());
+        assert!(query_product(&conn, &eraser.id).unwrap().is_none());
+        drop(conn);
+        assert!(db.check_integrity().unwrap().healthy);
+    }
 
-        Ok(SpinWheelResult {
-            winning_slot,
-            spent_points: spin_cost,
-            remaining_points,
-            student_id: req.student_id,
-            student_name,
-            record,
-        })
+    #[test]
+    fn rejects_invalid_bundle_rows() {
+        let db = TestDatabase::new();
+        let bundle = sample_bundle(&db);
+        validate_class_bundle(&bundle).unwrap();
+
+        let mut unnamed = bundle.clone();
+        unnamed.students[0].name = " ".to_string();
+        assert_eq!(validate_class_bundle(&unnamed).unwrap_err().code, ErrorCode::InvalidInput);
+
+        let mut unnumbered = bundle.clone();
+        unnumbered.students[1].student_number = String::new();
+        assert_eq!(validate_class_bundle(&unnumbered).unwrap_err().code, ErrorCode::InvalidInput);
+
+        let mut blank_reason = bundle.clone();
+        blank_reason.point_reasons[0].label = String::new();
+        assert_eq!(validate_class_bundle(&blank_reason).unwrap_err().code, ErrorCode::InvalidInput);
+
+        let mut zero_reason = bundle.clone();
+        zero_reason.point_reasons[0].default_delta = 0;
+        assert_eq!(validate_class_bundle(&zero_reason).unwrap_err().code, ErrorCode::InvalidInput);
+
+        let mut unknown_category = bundle.clone();
+        unknown_category.point_reasons[0].category = "bonus".to_string();
+        assert_eq!(validate_class_bundle(&unknown_category).unwrap_err().code, ErrorCode::InvalidInput);
     }
 
-    pub fn update_shipping_status(&self, record_id: &str, status: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE purchase_records SET shipping_status = ? WHERE id = ?",
-            params![status, record_id],
-        )?;
-        Ok(())
+    #[test]
+    fn rejects_wheel_slots_outside_bundled_products() {
+        let db = TestDatabase::new();
+        let mut bundle = sample_bundle(&db);
+
+        // Moving the wheel's product to the archive leaves its slot pointing at a trashed product
+        let pencil = bundle.products.remove(0);
+        bundle.archived_products.push(pencil);
+
+        let err = db
+            .import_class_bundle(ImportClassBundleRequest { bundle, mode: BUNDLE_IMPORT_NEW.to_string(), target_class_id: None })
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert_eq!(db.get_all_classes().unwrap().len(), 1);
     }
 }
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+/// Stable, machine-readable error codes. The frontend branches on these instead of
+/// matching message text; messages stay human-readable Chinese for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The requested class, student, product, record, … does not exist (or is in the recycle bin)
+    NotFound,
+    /// The request itself is malformed: empty names, out-of-range values, unknown options
+    InvalidInput,
+    InsufficientStock,
+    InsufficientPoints,
+    /// The operation would break a data rule, e.g. deleting a product still in use
+    ConstraintViolation,
+    WheelNotConfigured,
+    /// The target is not in a state that allows the operation, e.g. undoing twice
+    Conflict,
+    DatabaseError,
+    IoError,
+    InternalError,
+}
+
+/// Error returned by every `Database` method and Tauri command. Serializes as
+/// `{ code, message, details }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn insufficient_stock(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InsufficientStock, message)
+    }
+
+    pub fn insufficient_points(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InsufficientPoints, message)
+    }
+
+    pub fn constraint_violation(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::ConstraintViolation, message)
+    }
+
+    pub fn wheel_not_configured(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::WheelNotConfigured, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::IoError, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InternalError, message)
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match &e {
+            rusqlite::Error::QueryReturnedNoRows => Self::not_found("记录不存在"),
+            rusqlite::Error::SqliteFailure(failure, message) if failure.code == rusqlite::ErrorCode::ConstraintViolation => {
+                Self::constraint_violation(format!("数据约束冲突: {}", message.as_deref().unwrap_or("未知约束")))
+            }
+            _ => Self::new(ErrorCode::DatabaseError, format!("数据库错误: {}", e)),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::io(format!("文件操作失败: {}", e))
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::internal(format!("数据序列化失败: {}", e))
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        Self::internal(e.to_string())
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for AppError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        Self::internal(format!("生成 Excel 失败: {}", e))
+    }
+}
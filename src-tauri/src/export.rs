@@ -0,0 +1,220 @@
+use rust_xlsxwriter::{Format, Workbook};
+
+use crate::error::AppError;
+
+// Formats accepted by the export commands; also used as the file extension
+pub const EXPORT_FORMAT_XLSX: &str = "xlsx";
+pub const EXPORT_FORMAT_CSV: &str = "csv";
+/// A standalone page with print styles, for lists that get printed rather than edited
+pub const EXPORT_FORMAT_HTML: &str = "html";
+
+/// Excel only detects UTF-8 in a CSV file when it starts with a byte order mark.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub const STUDENT_EXPORT_HEADERS: &[&str] = &["排名", "学号", "学生姓名", "积分"];
+pub const PRODUCT_EXPORT_HEADERS: &[&str] = &["商品名称", "所需积分", "库存", "创建时间"];
+pub const PURCHASE_EXPORT_HEADERS: &[&str] = &["兑换时间", "学生姓名", "商品名称", "数量", "消耗积分", "来源", "发货状态", "发货时间", "领取时间"];
+pub const PICK_LIST_EXPORT_HEADERS: &[&str] = &["班级", "商品名称", "学生姓名", "学号", "数量", "已拣"];
+
+#[derive(Debug, Clone)]
+pub enum ExportCell {
+    Text(String),
+    Number(f64),
+}
+
+impl From<String> for ExportCell {
+    fn from(value: String) -> Self {
+        ExportCell::Text(value)
+    }
+}
+
+impl From<&str> for ExportCell {
+    fn from(value: &str) -> Self {
+        ExportCell::Text(value.to_string())
+    }
+}
+
+impl From<i32> for ExportCell {
+    fn from(value: i32) -> Self {
+        ExportCell::Number(value.into())
+    }
+}
+
+/// A rendered export, ready to be written to disk.
+#[derive(Debug, Clone)]
+pub struct ExportFile {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+    pub row_count: usize,
+}
+
+/// One sheet of exported data, rendered to either `.xlsx` or `.csv`.
+#[derive(Debug, Clone)]
+pub struct ExportTable {
+    pub sheet_name: &'static str,
+    pub headers: &'static [&'static str],
+    pub rows: Vec<Vec<ExportCell>>,
+}
+
+impl ExportTable {
+    /// Renders the table as `{title}_{date}.{format}`.
+    pub fn into_file(self, title: &str, format: &str) -> Result<ExportFile, AppError> {
+        Ok(ExportFile {
+            file_name: format!("{}_{}.{}", title, chrono::Local::now().format("%Y-%m-%d"), format),
+            bytes: self.render(title, format)?,
+            row_count: self.rows.len(),
+        })
+    }
+
+    /// `title` is only shown by formats that have a heading (HTML).
+    pub fn render(&self, title: &str, format: &str) -> Result<Vec<u8>, AppError> {
+        match format {
+            EXPORT_FORMAT_XLSX => self.to_xlsx(),
+            EXPORT_FORMAT_CSV => Ok(self.to_csv()),
+            EXPORT_FORMAT_HTML => Ok(self.to_html(title)),
+            _ => Err(AppError::invalid_input(format!("不支持的导出格式: {}", format))),
+        }
+    }
+
+    fn to_xlsx(&self) -> Result<Vec<u8>, AppError> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(self.sheet_name)?;
+
+        let header_format = Format::new().set_bold();
+        for (col, header) in self.headers.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, *header, &header_format)?;
+        }
+
+        // Character count is a good enough width for CJK text, which renders about two columns wide
+        let mut widths: Vec<usize> = self.headers.iter().map(|header| header.chars().count() * 2).collect();
+        for (row, cells) in self.rows.iter().enumerate() {
+            let row = row as u32 + 1;
+            for (col, cell) in cells.iter().enumerate() {
+                let width = match cell {
+                    ExportCell::Text(value) => {
+                        worksheet.write_string(row, col as u16, value)?;
+                        value.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+                    }
+                    ExportCell::Number(value) => {
+                        worksheet.write_number(row, col as u16, *value)?;
+                        value.to_string().len()
+                    }
+                };
+                if let Some(max) = widths.get_mut(col) {
+                    *max = (*max).max(width);
+                }
+            }
+        }
+
+        for (col, width) in widths.into_iter().enumerate() {
+            worksheet.set_column_width(col as u16, (width + 2).min(50) as f64)?;
+        }
+
+        Ok(workbook.save_to_buffer()?)
+    }
+
+    fn to_csv(&self) -> Vec<u8> {
+        let mut csv = String::new();
+        let headers: Vec<String> = self.headers.iter().map(|header| csv_field(header)).collect();
+        csv.push_str(&headers.join(","));
+        csv.push_str("\r\n");
+
+        for cells in &self.rows {
+            let fields: Vec<String> = cells
+                .iter()
+                .map(|cell| match cell {
+                    ExportCell::Text(value) => csv_field(value),
+                    ExportCell::Number(value) => value.to_string(),
+                })
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+
+        let mut bytes = UTF8_BOM.to_vec();
+        bytes.extend_from_slice(csv.as_bytes());
+        bytes
+    }
+
+    fn to_html(&self, title: &str) -> Vec<u8> {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", html_escape(title)));
+        html.push_str(HTML_STYLE);
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!(
+            "<h1>{}</h1>\n<p class=\"meta\">生成时间：{}</p>\n<table>\n<thead><tr>",
+            html_escape(title),
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        ));
+        for header in self.headers {
+            html.push_str(&format!("<th>{}</th>", html_escape(header)));
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+
+        for cells in &self.rows {
+            html.push_str("<tr>");
+            for cell in cells {
+                match cell {
+                    ExportCell::Text(value) => html.push_str(&format!("<td>{}</td>", html_escape(value))),
+                    ExportCell::Number(value) => html.push_str(&format!("<td class=\"number\">{}</td>", value)),
+                }
+            }
+            html.push_str("</tr>\n");
+        }
+
+        html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+        html.into_bytes()
+    }
+}
+
+const HTML_STYLE: &str = "<style>
+body { font-family: \"Microsoft YaHei\", \"PingFang SC\", sans-serif; margin: 24px; color: #111; }
+h1 { font-size: 20px; margin: 0 0 4px; }
+.meta { color: #666; font-size: 12px; margin: 0 0 12px; }
+table { border-collapse: collapse; width: 100%; font-size: 13px; }
+th, td { border: 1px solid #999; padding: 4px 8px; text-align: left; }
+th { background: #eee; }
+td.number { text-align: right; }
+thead { display: table-header-group; }
+tr { page-break-inside: avoid; }
+@media print { body { margin: 0; } }
+</style>
+";
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn csv_field(value: &str) -> String {
+    // Spreadsheets evaluate text starting with these as a formula, so user-entered names
+    // like "=HYPERLINK(...)" are neutralized with a leading apostrophe
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Display text for a shipping status, matching the purchase history page.
+pub fn shipping_status_label(status: &str) -> &str {
+    match status {
+        "pending" => "待发货",
+        "ready" => "待领取",
+        "shipped" => "已发货",
+        "received" => "已领取",
+        "cancelled" => "已取消",
+        "refunded" => "已退款",
+        other => other,
+    }
+}
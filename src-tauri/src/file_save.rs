@@ -0,0 +1,184 @@
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::app_config::AppConfig;
+use crate::models::SavedFile;
+
+const DEFAULT_FILE_NAME: &str = "未命名";
+const MAX_FILE_NAME_CHARS: usize = 180;
+const MAX_CONFLICT_SUFFIX: u32 = 9999;
+
+// Device names Windows refuses as file names, with or without an extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Reduces a caller-supplied name to a single safe path component: separators and
+/// characters invalid on Windows become `_`, and leading dots are dropped so names like
+/// `../x` or `.hidden` can neither escape the directory nor hide the file.
+pub fn sanitize_file_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+
+    let mut sanitized: String = replaced
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .chars()
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+
+    let stem = sanitized.split('.').next().unwrap_or_default().trim_end();
+    if WINDOWS_RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        sanitized.insert(0, '_');
+    }
+
+    if sanitized.trim_matches('_').is_empty() {
+        return DEFAULT_FILE_NAME.to_string();
+    }
+    sanitized
+}
+
+/// `dir/file_name` first, then `dir/stem(n).ext` for each conflict suffix in turn.
+fn candidate_paths<'a>(dir: &'a Path, file_name: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+    let (stem, extension) = match file_name.rfind('.') {
+        Some(index) if index > 0 => (&file_name[..index], &file_name[index..]),
+        _ => (file_name, ""),
+    };
+
+    std::iter::once(dir.join(file_name))
+        .chain((1..=MAX_CONFLICT_SUFFIX).map(move |n| dir.join(format!("{}({}){}", stem, n, extension))))
+}
+
+/// Desktop if there is one (XDG-aware on Linux), otherwise Documents, otherwise home.
+pub fn default_save_dir() -> Option<PathBuf> {
+    [dirs::desktop_dir(), dirs::document_dir(), dirs::home_dir()]
+        .into_iter()
+        .flatten()
+        .find(|dir| dir.is_dir())
+}
+
+/// Where a save without an explicit target goes: the last folder used, if it still exists,
+/// then the default.
+pub fn resolve_save_dir(config: &AppConfig) -> Result<PathBuf, AppError> {
+    config
+        .last_save_dir
+        .clone()
+        .filter(|dir| dir.is_dir())
+        .or_else(default_save_dir)
+        .ok_or_else(|| AppError::invalid_input("无法确定保存位置，请选择文件夹"))
+}
+
+/// Links the finished temporary file in at `path`, failing with `AlreadyExists` rather than
+/// replacing a file that is already there. Filesystems without hard links (FAT drives, some
+/// network shares) get the file created exclusively and written instead.
+fn install_no_clobber(temp_path: &Path, path: &Path, data: &[u8]) -> std::io::Result<()> {
+    match std::fs::hard_link(temp_path, path) {
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+            let written = file.write_all(data).and_then(|_| file.sync_all());
+            if written.is_err() {
+                drop(file);
+                let _ = std::fs::remove_file(path);
+            }
+            written
+        }
+        result => result,
+    }
+}
+
+/// Writes the data next to its destination under a temporary name, then installs it at the
+/// first free name for `file_name` in `dir`. A failed write never leaves a truncated file
+/// behind, and a file that appears at a name after it was picked is never overwritten.
+fn write_atomically(dir: &Path, file_name: &str, data: &[u8]) -> Result<PathBuf, AppError> {
+    let temp_path = dir.join(format!(".pointhub-{}.tmp", Uuid::new_v4()));
+
+    let result = (|| -> std::io::Result<Option<PathBuf>> {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        drop(file);
+
+        for path in candidate_paths(dir, file_name) {
+            match install_no_clobber(&temp_path, &path, data) {
+                Ok(()) => return Ok(Some(path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    })();
+
+    let _ = std::fs::remove_file(&temp_path);
+    match result {
+        Ok(Some(path)) => Ok(path),
+        Ok(None) => Err(AppError::conflict("同名文件过多，请更换文件名")),
+        Err(e) => Err(AppError::io(format!("文件保存失败: {}", e))),
+    }
+}
+
+/// Saves `data` as `file_name` in `target_dir` (or the remembered/default folder), never
+/// overwriting an existing file, and remembers the folder for next time.
+pub fn save_file(config_dir: &Path, target_dir: Option<&str>, file_name: &str, data: &[u8]) -> Result<SavedFile, AppError> {
+    let mut config = AppConfig::load(config_dir)?;
+
+    let dir = match target_dir.map(str::trim).filter(|dir| !dir.is_empty()) {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            if !dir.is_absolute() {
+                return Err(AppError::invalid_input("保存位置必须是绝对路径"));
+            }
+            if !dir.is_dir() {
+                return Err(AppError::not_found(format!("文件夹不存在: {}", dir.display())));
+            }
+            dir
+        }
+        None => resolve_save_dir(&config)?,
+    };
+
+    let path = write_atomically(&dir, &sanitize_file_name(file_name), data)?;
+
+    if config.last_save_dir.as_deref() != Some(dir.as_path()) {
+        config.last_save_dir = Some(dir.clone());
+        config.save(config_dir)?;
+    }
+
+    Ok(SavedFile {
+        path: path.to_string_lossy().to_string(),
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        directory: dir.to_string_lossy().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_never_overwrites_existing_file() {
+        let root = std::env::temp_dir().join(format!("pointhub-file-save-{}", Uuid::new_v4()));
+        let dir = root.join("out");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("名单.csv"), b"existing").unwrap();
+
+        let saved = save_file(&root.join("config"), Some(dir.to_str().unwrap()), "名单.csv", b"new").unwrap();
+
+        assert_eq!(saved.file_name, "名单(1).csv");
+        assert_eq!(std::fs::read(dir.join("名单.csv")).unwrap(), b"existing");
+        assert_eq!(std::fs::read(dir.join("名单(1).csv")).unwrap(), b"new");
+
+        // Only the two files remain; the temporary file is cleaned up
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
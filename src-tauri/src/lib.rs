@@ -1,5 +1,12 @@
+mod error;
 mod models;
 mod database;
+mod migrations;
+mod backup;
+mod app_config;
+mod student_import;
+mod export;
+mod file_save;
 mod commands;
 
 use database::Database;
@@ -20,23 +27,74 @@ pub fn run() {
             create_class,
             update_class,
             delete_class,
+            preview_class_deletion,
             get_students,
             get_students_by_class,
             create_student,
             update_student,
+            import_students_xlsx,
+            preview_student_import,
+            commit_student_import,
             delete_student,
+            transfer_student,
+            get_student_transfers,
+            adjust_student_points,
+            batch_adjust_points,
+            undo_point_operation,
+            undo_last_point_operations,
+            get_point_settings,
+            update_point_settings,
+            get_point_reasons_by_class,
+            create_point_reason,
+            update_point_reason,
+            delete_point_reason,
+            get_student_point_transactions,
+            get_point_transactions_paginated,
             get_products_by_class,
             create_product,
             update_product,
             delete_product,
-            save_file_to_desktop,
+            rollover_class,
+            get_archived_terms_by_class,
+            get_archived_term_detail,
+            check_integrity,
+            repair_integrity,
+            create_backup,
+            list_backups,
+            restore_backup,
+            get_backup_settings,
+            update_backup_settings,
+            get_data_location,
+            move_data_location,
+            detect_legacy_databases,
+            export_class_bundle,
+            preview_class_bundle_import,
+            import_class_bundle,
+            list_trash,
+            restore_class,
+            restore_student,
+            restore_product,
+            purge_trash,
+            save_file,
+            get_save_location,
+            export_students,
+            export_products,
+            export_purchase_records,
+            export_pick_list,
             create_purchase_record,
             get_purchase_records_by_class,
             get_purchase_records_paginated,
             update_shipping_status,
+            ship_purchase_records,
+            ship_student_pending_records,
+            ship_class_pending_records,
+            get_pick_list,
+            refund_purchase_record,
             get_wheel_config,
             save_wheel_config,
-            spin_wheel
+            spin_wheel,
+            get_audit_logs_paginated,
+            get_schema_info
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
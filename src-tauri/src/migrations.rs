@@ -0,0 +1,637 @@
+use std::path::{Path, PathBuf};
+use rusqlite::{Connection, Transaction, Result as SqliteResult, OptionalExtension, params};
+use chrono::Utc;
+
+use crate::backup;
+use crate::error::{AppError, ErrorCode};
+
+/// A numbered schema change. `version` is written to `PRAGMA user_version`
+/// and recorded in `schema_migrations` once `up` has committed.
+struct Migration {
+    version: i64,
+    name: &'static str,
+    up: fn(&Transaction) -> SqliteResult<()>,
+}
+
+// Append new migrations at the end. Never edit or renumber a migration that has shipped.
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline", up: migrate_v1_baseline },
+    Migration { version: 2, name: "point_transactions", up: migrate_v2_point_transactions },
+    Migration { version: 3, name: "app_settings", up: migrate_v3_app_settings },
+    Migration { version: 4, name: "point_reasons", up: migrate_v4_point_reasons },
+    Migration { version: 5, name: "point_transaction_reversals", up: migrate_v5_point_transaction_reversals },
+    Migration { version: 6, name: "audit_logs", up: migrate_v6_audit_logs },
+    Migration { version: 7, name: "soft_delete", up: migrate_v7_soft_delete },
+    Migration { version: 8, name: "archived_terms", up: migrate_v8_archived_terms },
+    Migration { version: 9, name: "student_transfers", up: migrate_v9_student_transfers },
+    Migration { version: 10, name: "purchase_refunds", up: migrate_v10_purchase_refunds },
+    Migration { version: 11, name: "shipping_timestamps", up: migrate_v11_shipping_timestamps },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> SqliteResult<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the database up to `latest_version()`.
+///
+/// Each migration runs in its own transaction. A copy of the database is written to
+/// `backups/` before the first pending migration touches an existing install, and any
+/// failure aborts startup instead of leaving a half-migrated schema behind.
+pub fn run_migrations(conn: &mut Connection, db_path: &Path) -> Result<(), AppError> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(AppError::conflict(format!(
+            "数据库版本 v{} 高于当前程序支持的版本 v{}，请升级程序后再打开",
+            current, latest
+        )));
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let table_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        [],
+        |row| row.get(0),
+    )?;
+    if table_count > 0 {
+        backup_before_migration(conn, db_path, current)?;
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Table rebuilds need foreign keys off, and the pragma is a no-op inside a transaction.
+    let foreign_keys_enabled: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.execute("PRAGMA foreign_keys = OFF", [])?;
+
+    let migration_result: Result<(), AppError> = (|| {
+        for migration in pending {
+            let tx = conn.transaction()?;
+            (migration.up)(&tx).map_err(|e| {
+                AppError::new(ErrorCode::DatabaseError, format!("数据库迁移 v{} ({}) 失败: {}", migration.version, migration.name, e))
+            })?;
+            tx.execute(
+                "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+                params![migration.version, migration.name, Utc::now().to_rfc3339()],
+            )?;
+            tx.pragma_update(None, "user_version", migration.version)?;
+            tx.commit()?;
+        }
+        Ok(())
+    })();
+
+    if foreign_keys_enabled {
+        conn.execute("PRAGMA foreign_keys = ON", [])?;
+    }
+
+    migration_result
+}
+
+fn backup_before_migration(conn: &Connection, db_path: &Path, from_version: i64) -> Result<PathBuf, AppError> {
+    let backup_path = backup::backup_dir(db_path)?.join(format!(
+        "pointhub-v{}-{}-{}.db",
+        from_version,
+        backup::BACKUP_KIND_PRE_MIGRATION_SUFFIX,
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy().to_string()])
+        .map_err(|e| AppError::io(format!("迁移前备份失败: {}", e)))?;
+
+    Ok(backup_path)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> SqliteResult<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// v1 absorbs every schema the app has shipped before versioning existed, so installs
+/// with `user_version = 0` converge on the same tables no matter which release created them.
+fn migrate_v1_baseline(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS classes (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            student_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS students (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            student_number TEXT NOT NULL DEFAULT '',
+            points INTEGER NOT NULL DEFAULT 0,
+            class_id TEXT NOT NULL,
+            class_name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        )",
+        [],
+    )?;
+
+    add_column_if_missing(tx, "students", "created_at", "TEXT DEFAULT ''")?;
+    add_column_if_missing(tx, "students", "student_number", "TEXT DEFAULT ''")?;
+
+    // Update any existing students without created_at
+    tx.execute(
+        "UPDATE students SET created_at = ? WHERE created_at = '' OR created_at IS NULL",
+        [&Utc::now().to_rfc3339()],
+    )?;
+
+    // Update any existing students without student_number (generate default)
+    tx.execute(
+        "UPDATE students SET student_number = 'STU' || substr(id, 1, 8) WHERE student_number = '' OR student_number IS NULL",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS products (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            stock INTEGER NOT NULL,
+            class_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS purchase_records (
+            id TEXT PRIMARY KEY,
+            product_id TEXT NOT NULL,
+            product_name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            student_id TEXT NOT NULL,
+            student_name TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            class_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            shipping_status TEXT NOT NULL DEFAULT 'pending',
+            source TEXT NOT NULL DEFAULT '购买',
+            FOREIGN KEY(student_id) REFERENCES students(id),
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        )",
+        [],
+    )?;
+
+    add_column_if_missing(tx, "purchase_records", "shipping_status", "TEXT DEFAULT 'pending'")?;
+    add_column_if_missing(tx, "purchase_records", "source", "TEXT DEFAULT '购买'")?;
+
+    // Backfill source for historical records
+    tx.execute(
+        "UPDATE purchase_records SET source = '购买' WHERE source = '' OR source IS NULL",
+        [],
+    )?;
+
+    // One wheel config per class
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS wheel_configs (
+            class_id TEXT PRIMARY KEY,
+            spin_cost INTEGER NOT NULL DEFAULT 10,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        )",
+        [],
+    )?;
+
+    // Wheel slots control probability via duplicated slots.
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS wheel_slots (
+            id TEXT PRIMARY KEY,
+            class_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            slot_index INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id),
+            FOREIGN KEY(product_id) REFERENCES products(id)
+        )",
+        [],
+    )?;
+
+    // Rebuild purchase_records table if old schema still contains product_id foreign key
+    let schema_sql: Option<String> = tx
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'purchase_records'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(schema_sql) = schema_sql {
+        if schema_sql.contains("FOREIGN KEY(\"product_id\")")
+            || schema_sql.contains("FOREIGN KEY(product_id)")
+        {
+            tx.execute("DROP TABLE IF EXISTS purchase_records_new", [])?;
+            tx.execute(
+                "CREATE TABLE purchase_records_new (
+                    id TEXT PRIMARY KEY,
+                    product_id TEXT NOT NULL,
+                    product_name TEXT NOT NULL,
+                    points INTEGER NOT NULL,
+                    student_id TEXT NOT NULL,
+                    student_name TEXT NOT NULL,
+                    quantity INTEGER NOT NULL,
+                    class_id TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    shipping_status TEXT NOT NULL DEFAULT 'pending',
+                    source TEXT NOT NULL DEFAULT '购买',
+                    FOREIGN KEY(student_id) REFERENCES students(id),
+                    FOREIGN KEY(class_id) REFERENCES classes(id)
+                )",
+                [],
+            )?;
+
+            tx.execute(
+                "INSERT INTO purchase_records_new (
+                    id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, shipping_status, source
+                )
+                SELECT
+                    id, product_id, product_name, points, student_id, student_name, quantity, class_id, created_at, COALESCE(shipping_status, 'pending'), COALESCE(source, '购买')
+                FROM purchase_records",
+                [],
+            )?;
+
+            tx.execute("DROP TABLE purchase_records", [])?;
+            tx.execute(
+                "ALTER TABLE purchase_records_new RENAME TO purchase_records",
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Every change to `students.points` is appended here. Existing balances are carried in
+/// as one opening entry per student so that each student's ledger sums to their balance.
+fn migrate_v2_point_transactions(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS point_transactions (
+            id TEXT PRIMARY KEY,
+            student_id TEXT NOT NULL,
+            class_id TEXT NOT NULL,
+            delta INTEGER NOT NULL,
+            balance_after INTEGER NOT NULL,
+            reason TEXT,
+            source TEXT NOT NULL,
+            related_id TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_point_transactions_student ON point_transactions(student_id, created_at)",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_point_transactions_class ON point_transactions(class_id, created_at)",
+        [],
+    )?;
+
+    tx.execute(
+        "INSERT INTO point_transactions (id, student_id, class_id, delta, balance_after, reason, source, related_id, created_at)
+         SELECT lower(hex(randomblob(16))), id, class_id, points, points, '历史积分结转', 'opening', NULL, ?1
+         FROM students
+         WHERE points <> 0",
+        [Utc::now().to_rfc3339()],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_v3_app_settings(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_v4_point_reasons(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS point_reasons (
+            id TEXT PRIMARY KEY,
+            class_id TEXT NOT NULL,
+            label TEXT NOT NULL,
+            default_delta INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_point_reasons_class ON point_reasons(class_id, sort_order)",
+        [],
+    )?;
+
+    add_column_if_missing(tx, "point_transactions", "reason_id", "TEXT")?;
+
+    Ok(())
+}
+
+/// Undo never deletes ledger rows; it links the original entry and its compensating entry.
+/// Rows written by one batch adjustment share an `operation_id` so they are undone together.
+fn migrate_v5_point_transaction_reversals(tx: &Transaction) -> SqliteResult<()> {
+    add_column_if_missing(tx, "point_transactions", "reverts_id", "TEXT")?;
+    add_column_if_missing(tx, "point_transactions", "reverted_by", "TEXT")?;
+    add_column_if_missing(tx, "point_transactions", "operation_id", "TEXT")?;
+
+    Ok(())
+}
+
+fn migrate_v6_audit_logs(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS audit_logs (
+            id TEXT PRIMARY KEY,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            class_id TEXT,
+            action TEXT NOT NULL,
+            before_json TEXT,
+            after_json TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_logs_class ON audit_logs(class_id, created_at)",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_logs_entity ON audit_logs(entity_type, entity_id, created_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Rows with `deleted_at` set are in the recycle bin and hidden from every list query.
+fn migrate_v7_soft_delete(tx: &Transaction) -> SqliteResult<()> {
+    add_column_if_missing(tx, "classes", "deleted_at", "TEXT")?;
+    add_column_if_missing(tx, "students", "deleted_at", "TEXT")?;
+    add_column_if_missing(tx, "products", "deleted_at", "TEXT")?;
+
+    Ok(())
+}
+
+/// Read-only snapshots taken by a semester rollover. Purchase records are copied rather
+/// than referenced so a term report is unaffected by later edits or purges.
+fn migrate_v8_archived_terms(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS archived_terms (
+            id TEXT PRIMARY KEY,
+            class_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            balance_policy TEXT NOT NULL,
+            carry_percent INTEGER,
+            student_count INTEGER NOT NULL,
+            total_points INTEGER NOT NULL,
+            purchase_count INTEGER NOT NULL,
+            wheel_config_json TEXT,
+            period_start TEXT,
+            archived_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS term_student_balances (
+            term_id TEXT NOT NULL,
+            student_id TEXT NOT NULL,
+            student_name TEXT NOT NULL,
+            student_number TEXT NOT NULL,
+            final_points INTEGER NOT NULL,
+            carried_points INTEGER NOT NULL,
+            PRIMARY KEY (term_id, student_id),
+            FOREIGN KEY (term_id) REFERENCES archived_terms (id)
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS term_purchase_records (
+            term_id TEXT NOT NULL,
+            id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            product_name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            student_id TEXT NOT NULL,
+            student_name TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            class_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            shipping_status TEXT NOT NULL,
+            source TEXT NOT NULL,
+            PRIMARY KEY (term_id, id),
+            FOREIGN KEY (term_id) REFERENCES archived_terms (id)
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_archived_terms_class ON archived_terms(class_id, archived_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_v9_student_transfers(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS student_transfers (
+            id TEXT PRIMARY KEY,
+            student_id TEXT NOT NULL,
+            from_class_id TEXT NOT NULL,
+            to_class_id TEXT NOT NULL,
+            history_policy TEXT NOT NULL,
+            purchase_records_moved INTEGER NOT NULL,
+            point_transactions_moved INTEGER NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_student_transfers_student ON student_transfers(student_id, created_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_v10_purchase_refunds(tx: &Transaction) -> SqliteResult<()> {
+    // Archived terms keep a copy of purchase records, so both tables get the column
+    add_column_if_missing(tx, "purchase_records", "refunded_quantity", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "term_purchase_records", "refunded_quantity", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn migrate_v11_shipping_timestamps(tx: &Transaction) -> SqliteResult<()> {
+    for table in ["purchase_records", "term_purchase_records"] {
+        add_column_if_missing(tx, table, "shipped_at", "TEXT")?;
+        add_column_if_missing(tx, table, "received_at", "TEXT")?;
+
+        // `delivered` was the old name for `shipped`; when it happened was never recorded
+        tx.execute(
+            &format!("UPDATE {} SET shipping_status = 'shipped' WHERE shipping_status = 'delivered'", table),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The schema `init_tables` created before migrations existed.
+    const LEGACY_SCHEMA: &str = "
+        CREATE TABLE classes (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            student_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE students (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            student_number TEXT NOT NULL DEFAULT '',
+            points INTEGER NOT NULL DEFAULT 0,
+            class_id TEXT NOT NULL,
+            class_name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        );
+        CREATE TABLE products (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            stock INTEGER NOT NULL,
+            class_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        );
+        CREATE TABLE purchase_records (
+            id TEXT PRIMARY KEY,
+            product_id TEXT NOT NULL,
+            product_name TEXT NOT NULL,
+            points INTEGER NOT NULL,
+            student_id TEXT NOT NULL,
+            student_name TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            class_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            shipping_status TEXT NOT NULL DEFAULT 'pending',
+            source TEXT NOT NULL DEFAULT '购买',
+            FOREIGN KEY(student_id) REFERENCES students(id),
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        );
+        CREATE TABLE wheel_configs (
+            class_id TEXT PRIMARY KEY,
+            spin_cost INTEGER NOT NULL DEFAULT 10,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id)
+        );
+        CREATE TABLE wheel_slots (
+            id TEXT PRIMARY KEY,
+            class_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            slot_index INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(class_id) REFERENCES classes(id),
+            FOREIGN KEY(product_id) REFERENCES products(id)
+        );
+
+        INSERT INTO classes VALUES ('c1', '一班', NULL, 2, '2024-01-01T00:00:00+00:00');
+        INSERT INTO students VALUES ('s1', '张三', '1', 85, 'c1', '一班', '2024-01-01T00:00:00+00:00');
+        INSERT INTO students VALUES ('s2', '李四', '2', 0, 'c1', '一班', '2024-01-01T00:00:00+00:00');
+        INSERT INTO products VALUES ('p1', '铅笔', 5, 10, 'c1', '2024-01-01T00:00:00+00:00');
+        INSERT INTO purchase_records VALUES
+            ('r1', 'p1', '铅笔', 5, 's1', '张三', 1, 'c1', '2024-02-01T00:00:00+00:00', 'delivered', '购买'),
+            ('r2', 'p1', '铅笔', 10, 's1', '张三', 2, 'c1', '2024-03-01T00:00:00+00:00', 'pending', '购买');
+    ";
+
+    #[test]
+    fn migrates_legacy_schema() {
+        let dir = std::env::temp_dir().join(format!("pointhub-migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(LEGACY_SCHEMA).unwrap();
+        run_migrations(&mut conn, &dir.join("pointhub.db")).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        let applied: i64 = conn.query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0)).unwrap();
+        assert_eq!(applied, MIGRATIONS.len() as i64);
+
+        // One opening row per non-zero balance, so every ledger sums to the balance
+        let ledger: Vec<(String, i32, i32, String)> = conn
+            .prepare("SELECT student_id, delta, balance_after, source FROM point_transactions")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(ledger, vec![("s1".to_string(), 85, 85, "opening".to_string())]);
+
+        let status = |id: &str| -> String {
+            conn.query_row("SELECT shipping_status FROM purchase_records WHERE id = ?", [id], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(status("r1"), "shipped");
+        assert_eq!(status("r2"), "pending");
+
+        assert!(std::fs::read_dir(dir.join("backups")).unwrap().next().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -47,11 +47,12 @@ pub struct CreateStudentRequest {
     pub class_id: String,
 }
 
+/// Points are not editable here; they change only through `adjust_student_points`, so the
+/// floor applies and concurrent adjustments are never overwritten.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateStudentRequest {
     pub name: Option<String>,
     pub student_number: Option<String>,
-    pub points: Option<i32>,
     pub class_id: Option<String>,
 }
 
@@ -102,6 +103,13 @@ pub struct PurchaseRecord {
     #[serde(rename = "shippingStatus")]
     pub shipping_status: String,
     pub source: String,
+    /// Units already refunded; `quantity` is never reduced
+    #[serde(rename = "refundedQuantity", default)]
+    pub refunded_quantity: i32,
+    #[serde(rename = "shippedAt", default)]
+    pub shipped_at: Option<DateTime<Utc>>,
+    #[serde(rename = "receivedAt", default)]
+    pub received_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +124,74 @@ pub struct UpdateShippingStatusRequest {
     pub shipping_status: String,
 }
 
+/// `quantity` defaults to every unit not yet refunded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundPurchaseRequest {
+    pub quantity: Option<i32>,
+    pub reason: Option<String>,
+}
+
+/// Outcome of a bulk "mark as shipped". `skipped` counts records that were already shipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkShippingResult {
+    pub updated: i32,
+    pub skipped: i32,
+}
+
+/// What to pull from the cupboard: units still owed on `pending` records, per product and
+/// then per student. `class_id` is `None` for a list across all classes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickList {
+    #[serde(rename = "classId")]
+    pub class_id: Option<String>,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: DateTime<Utc>,
+    #[serde(rename = "totalQuantity")]
+    pub total_quantity: i32,
+    #[serde(rename = "recordCount")]
+    pub record_count: i32,
+    pub products: Vec<PickListProduct>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickListProduct {
+    #[serde(rename = "productId")]
+    pub product_id: String,
+    #[serde(rename = "productName")]
+    pub product_name: String,
+    #[serde(rename = "classId")]
+    pub class_id: String,
+    #[serde(rename = "className")]
+    pub class_name: String,
+    #[serde(rename = "totalQuantity")]
+    pub total_quantity: i32,
+    pub students: Vec<PickListStudent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickListStudent {
+    #[serde(rename = "studentId")]
+    pub student_id: String,
+    #[serde(rename = "studentName")]
+    pub student_name: String,
+    #[serde(rename = "studentNumber")]
+    pub student_number: Option<String>,
+    pub quantity: i32,
+    #[serde(rename = "recordCount")]
+    pub record_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundPurchaseResult {
+    pub record: PurchaseRecord,
+    #[serde(rename = "refundedQuantity")]
+    pub refunded_quantity: i32,
+    #[serde(rename = "refundedPoints")]
+    pub refunded_points: i32,
+    #[serde(rename = "studentPoints")]
+    pub student_points: i32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedPurchaseRecords {
     pub records: Vec<PurchaseRecord>,
@@ -186,3 +262,531 @@ pub struct SpinWheelResult {
     pub student_name: String,
     pub record: PurchaseRecord,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    #[serde(rename = "appliedAt")]
+    pub applied_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaInfo {
+    pub version: i64,
+    #[serde(rename = "latestVersion")]
+    pub latest_version: i64,
+    pub migrations: Vec<AppliedMigration>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointTransaction {
+    pub id: String,
+    #[serde(rename = "studentId")]
+    pub student_id: String,
+    #[serde(rename = "classId")]
+    pub class_id: String,
+    pub delta: i32,
+    #[serde(rename = "balanceAfter")]
+    pub balance_after: i32,
+    pub reason: Option<String>,
+    pub source: String,
+    #[serde(rename = "relatedId")]
+    pub related_id: Option<String>,
+    #[serde(rename = "reasonId")]
+    pub reason_id: Option<String>,
+    #[serde(rename = "revertsId")]
+    pub reverts_id: Option<String>,
+    #[serde(rename = "revertedBy")]
+    pub reverted_by: Option<String>,
+    #[serde(rename = "operationId")]
+    pub operation_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatedPointTransactions {
+    pub transactions: Vec<PointTransaction>,
+    pub total: i64,
+    #[serde(rename = "totalPages")]
+    pub total_pages: i64,
+    #[serde(rename = "currentPage")]
+    pub current_page: i64,
+    #[serde(rename = "pageSize")]
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointAdjustmentResult {
+    #[serde(rename = "studentId")]
+    pub student_id: String,
+    #[serde(rename = "studentName")]
+    pub student_name: String,
+    pub delta: i32,
+    pub balance: i32,
+    #[serde(rename = "transactionId")]
+    pub transaction_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointSettings {
+    #[serde(rename = "minPoints")]
+    pub min_points: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchAdjustPointsRequest {
+    pub student_ids: Option<Vec<String>>,
+    pub class_id: Option<String>,
+    pub delta: i32,
+    pub reason: Option<String>,
+    pub reason_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchAdjustPointsResult {
+    #[serde(rename = "operationId")]
+    pub operation_id: String,
+    pub results: Vec<PointAdjustmentResult>,
+    #[serde(rename = "totalStudents")]
+    pub total_students: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointReason {
+    pub id: String,
+    #[serde(rename = "classId")]
+    pub class_id: String,
+    pub label: String,
+    #[serde(rename = "defaultDelta")]
+    pub default_delta: i32,
+    pub category: String,
+    #[serde(rename = "sortOrder")]
+    pub sort_order: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePointReasonRequest {
+    pub class_id: String,
+    pub label: String,
+    pub default_delta: i32,
+    pub category: Option<String>,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePointReasonRequest {
+    pub label: Option<String>,
+    pub default_delta: Option<i32>,
+    pub category: Option<String>,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub id: String,
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: String,
+    #[serde(rename = "classId")]
+    pub class_id: Option<String>,
+    pub action: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatedAuditLogs {
+    pub logs: Vec<AuditLog>,
+    pub total: i64,
+    #[serde(rename = "totalPages")]
+    pub total_pages: i64,
+    #[serde(rename = "currentPage")]
+    pub current_page: i64,
+    #[serde(rename = "pageSize")]
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassDeletionPreview {
+    #[serde(rename = "classId")]
+    pub class_id: String,
+    #[serde(rename = "className")]
+    pub class_name: String,
+    pub students: i32,
+    pub products: i32,
+    #[serde(rename = "trashedStudents")]
+    pub trashed_students: i32,
+    #[serde(rename = "trashedProducts")]
+    pub trashed_products: i32,
+    #[serde(rename = "purchaseRecords")]
+    pub purchase_records: i32,
+    #[serde(rename = "wheelSlots")]
+    pub wheel_slots: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "classId")]
+    pub class_id: Option<String>,
+    #[serde(rename = "className")]
+    pub class_name: Option<String>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeTrashResult {
+    pub classes: i32,
+    pub students: i32,
+    pub products: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloverClassRequest {
+    pub term_name: String,
+    pub balance_policy: String,
+    pub carry_percent: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTerm {
+    pub id: String,
+    #[serde(rename = "classId")]
+    pub class_id: String,
+    pub name: String,
+    #[serde(rename = "balancePolicy")]
+    pub balance_policy: String,
+    #[serde(rename = "carryPercent")]
+    pub carry_percent: Option<i32>,
+    #[serde(rename = "studentCount")]
+    pub student_count: i32,
+    #[serde(rename = "totalPoints")]
+    pub total_points: i64,
+    #[serde(rename = "purchaseCount")]
+    pub purchase_count: i32,
+    #[serde(rename = "wheelConfig")]
+    pub wheel_config: Option<serde_json::Value>,
+    #[serde(rename = "periodStart")]
+    pub period_start: Option<DateTime<Utc>>,
+    #[serde(rename = "archivedAt")]
+    pub archived_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TermStudentBalance {
+    #[serde(rename = "studentId")]
+    pub student_id: String,
+    #[serde(rename = "studentName")]
+    pub student_name: String,
+    #[serde(rename = "studentNumber")]
+    pub student_number: String,
+    #[serde(rename = "finalPoints")]
+    pub final_points: i32,
+    #[serde(rename = "carriedPoints")]
+    pub carried_points: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTermDetail {
+    pub term: ArchivedTerm,
+    pub balances: Vec<TermStudentBalance>,
+    #[serde(rename = "purchaseRecords")]
+    pub purchase_records: Vec<PurchaseRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStudentRequest {
+    pub to_class_id: String,
+    pub history_policy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentTransfer {
+    pub id: String,
+    #[serde(rename = "studentId")]
+    pub student_id: String,
+    #[serde(rename = "fromClassId")]
+    pub from_class_id: String,
+    #[serde(rename = "toClassId")]
+    pub to_class_id: String,
+    #[serde(rename = "historyPolicy")]
+    pub history_policy: String,
+    #[serde(rename = "purchaseRecordsMoved")]
+    pub purchase_records_moved: i32,
+    #[serde(rename = "pointTransactionsMoved")]
+    pub point_transactions_moved: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStudentResult {
+    pub student: Student,
+    pub transfer: StudentTransfer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub code: String,
+    pub description: String,
+    pub count: i64,
+    #[serde(rename = "sampleIds")]
+    pub sample_ids: Vec<String>,
+    pub repairable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub healthy: bool,
+    pub issues: Vec<IntegrityIssue>,
+    #[serde(rename = "checkedAt")]
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityRepair {
+    pub code: String,
+    pub fixed: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairIntegrityResult {
+    pub repairs: Vec<IntegrityRepair>,
+    pub report: IntegrityReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub kind: String,
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: Option<i64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSettings {
+    #[serde(rename = "retentionCount")]
+    pub retention_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreBackupResult {
+    pub restored: BackupInfo,
+    #[serde(rename = "safetyBackup")]
+    pub safety_backup: BackupInfo,
+    #[serde(rename = "schemaInfo")]
+    pub schema_info: SchemaInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataLocation {
+    #[serde(rename = "dataDir")]
+    pub data_dir: String,
+    #[serde(rename = "dbPath")]
+    pub db_path: String,
+    #[serde(rename = "settingsPath")]
+    pub settings_path: String,
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedDatabase {
+    #[serde(rename = "dataDir")]
+    pub data_dir: String,
+    #[serde(rename = "dbPath")]
+    pub db_path: String,
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: Option<i64>,
+    #[serde(rename = "modifiedAt")]
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// A whole class in one portable, versioned document. Students and products that are in
+/// the recycle bin but still referenced by purchase records travel as `archived*`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassBundle {
+    pub format: String,
+    pub version: i32,
+    #[serde(rename = "exportedAt")]
+    pub exported_at: DateTime<Utc>,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: i64,
+    pub class: Class,
+    pub students: Vec<Student>,
+    #[serde(rename = "archivedStudents", default)]
+    pub archived_students: Vec<Student>,
+    pub products: Vec<Product>,
+    #[serde(rename = "archivedProducts", default)]
+    pub archived_products: Vec<Product>,
+    #[serde(rename = "purchaseRecords", default)]
+    pub purchase_records: Vec<PurchaseRecord>,
+    #[serde(rename = "pointReasons", default)]
+    pub point_reasons: Vec<PointReason>,
+    #[serde(rename = "wheelConfig", default)]
+    pub wheel_config: Option<BundleWheelConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleWheelConfig {
+    #[serde(rename = "spinCost")]
+    pub spin_cost: i32,
+    /// Product ids in slot order
+    #[serde(rename = "productIds")]
+    pub product_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassBundleImportPreview {
+    #[serde(rename = "className")]
+    pub class_name: String,
+    pub students: i32,
+    pub products: i32,
+    #[serde(rename = "purchaseRecords")]
+    pub purchase_records: i32,
+    #[serde(rename = "hasWheelConfig")]
+    pub has_wheel_config: bool,
+    /// Existing classes with the same id or name
+    pub collisions: Vec<Class>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportClassBundleRequest {
+    pub bundle: ClassBundle,
+    pub mode: String,
+    pub target_class_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportClassBundleResult {
+    pub class: Class,
+    #[serde(rename = "studentsCreated")]
+    pub students_created: i32,
+    #[serde(rename = "studentsMerged")]
+    pub students_merged: i32,
+    #[serde(rename = "productsCreated")]
+    pub products_created: i32,
+    #[serde(rename = "productsMerged")]
+    pub products_merged: i32,
+    #[serde(rename = "purchaseRecordsImported")]
+    pub purchase_records_imported: i32,
+    #[serde(rename = "purchaseRecordsSkipped")]
+    pub purchase_records_skipped: i32,
+    #[serde(rename = "wheelConfigImported")]
+    pub wheel_config_imported: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentImportError {
+    /// 1-based row number as shown in Excel
+    pub row: u32,
+    pub field: String,
+    pub value: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentImportReport {
+    #[serde(rename = "totalRows")]
+    pub total_rows: i32,
+    #[serde(rename = "importedCount")]
+    pub imported_count: i32,
+    pub students: Vec<Student>,
+    pub errors: Vec<StudentImportError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentFieldChange {
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// What importing one roster row will do: `create`, `update`, `unchanged` or `conflict`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentImportPlanRow {
+    pub row: u32,
+    pub action: String,
+    #[serde(rename = "studentNumber")]
+    pub student_number: String,
+    pub name: String,
+    /// `None` when the 积分 cell was blank, which keeps an existing student's points
+    pub points: Option<i32>,
+    #[serde(rename = "studentId")]
+    pub student_id: Option<String>,
+    pub changes: Vec<StudentFieldChange>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentImportPreview {
+    #[serde(rename = "totalRows")]
+    pub total_rows: i32,
+    pub rows: Vec<StudentImportPlanRow>,
+    pub errors: Vec<StudentImportError>,
+    #[serde(rename = "createCount")]
+    pub create_count: i32,
+    #[serde(rename = "updateCount")]
+    pub update_count: i32,
+    #[serde(rename = "unchangedCount")]
+    pub unchanged_count: i32,
+    #[serde(rename = "conflictCount")]
+    pub conflict_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitStudentImportRequest {
+    pub rows: Vec<StudentImportPlanRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitStudentImportResult {
+    pub created: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub students: Vec<Student>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPurchaseRecordsRequest {
+    /// Inclusive local dates, `YYYY-MM-DD`
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub source: Option<String>,
+    pub shipping_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportFileResult {
+    pub path: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "rowCount")]
+    pub row_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFile {
+    pub path: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub directory: String,
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+
+use crate::error::AppError;
+use crate::models::StudentImportError;
+
+// Field names reported in StudentImportError.field
+pub const FIELD_STUDENT_NUMBER: &str = "studentNumber";
+pub const FIELD_NAME: &str = "name";
+pub const FIELD_POINTS: &str = "points";
+
+const HEADER_STUDENT_NUMBER: &[&str] = &["学号"];
+const HEADER_NAME: &[&str] = &["学生姓名", "姓名"];
+const HEADER_POINTS: &[&str] = &["积分"];
+
+/// A roster row that passed validation. `row` is the 1-based row number as shown in Excel;
+/// `points` is `None` when the 积分 cell was left blank.
+#[derive(Debug, Clone)]
+pub struct StudentImportRow {
+    pub row: u32,
+    pub student_number: String,
+    pub name: String,
+    pub points: Option<i32>,
+}
+
+/// Result of reading a roster: the rows that passed validation and the problems found in
+/// the others. `total_rows` counts every non-blank data row.
+#[derive(Debug, Clone)]
+pub struct StudentRoster {
+    pub total_rows: usize,
+    pub rows: Vec<StudentImportRow>,
+    pub errors: Vec<StudentImportError>,
+}
+
+/// Column positions, taken from the header row when it has the template's titles and
+/// falling back to the template order (学号, 学生姓名, 积分) otherwise.
+struct Columns {
+    student_number: usize,
+    name: usize,
+    points: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[Data]) -> Self {
+        let find = |titles: &[&str]| header.iter().position(|cell| titles.contains(&cell_text(cell).as_str()));
+
+        match (find(HEADER_STUDENT_NUMBER), find(HEADER_NAME)) {
+            (Some(student_number), Some(name)) => Self { student_number, name, points: find(HEADER_POINTS) },
+            _ => Self { student_number: 0, name: 1, points: Some(2) },
+        }
+    }
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(value) => value.trim().to_string(),
+        Data::Float(value) if value.fract() == 0.0 => format!("{}", *value as i64),
+        other => other.to_string().trim().to_string(),
+    }
+}
+
+/// `Ok(None)` for a blank cell, `Err(())` for anything that is not an integer.
+fn cell_points(cell: &Data) -> Result<Option<i32>, ()> {
+    match cell {
+        Data::Empty => Ok(None),
+        Data::Int(value) => i32::try_from(*value).map(Some).map_err(|_| ()),
+        Data::Float(value) if value.fract() == 0.0 && value.abs() <= i32::MAX as f64 => Ok(Some(*value as i32)),
+        Data::String(value) if value.trim().is_empty() => Ok(None),
+        Data::String(value) => value.trim().parse().map(Some).map_err(|_| ()),
+        _ => Err(()),
+    }
+}
+
+fn row_error(row: u32, field: &str, value: String, message: &str) -> StudentImportError {
+    StudentImportError {
+        row,
+        field: field.to_string(),
+        value,
+        message: message.to_string(),
+    }
+}
+
+/// Reads the first sheet of an `.xlsx`/`.xls` roster and validates every row on its own.
+/// Blank rows are skipped; a row with any problem is reported and left out of the result.
+/// Checks that need the database (学号 already taken in the class) are left to the caller.
+pub fn read_student_rows(bytes: Vec<u8>) -> Result<StudentRoster, AppError> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+        .map_err(|e| AppError::invalid_input(format!("Excel文件解析失败: {}", e)))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| AppError::invalid_input("Excel文件中没有工作表"))?
+        .map_err(|e| AppError::invalid_input(format!("Excel文件解析失败: {}", e)))?;
+
+    let first_row = range.start().map(|(row, _)| row).unwrap_or_default();
+    let mut rows = range.rows();
+    let columns = Columns::from_header(rows.next().ok_or_else(|| AppError::invalid_input("Excel文件内容为空或格式错误"))?);
+
+    let mut total_rows = 0;
+    let mut valid = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen_numbers: HashMap<String, u32> = HashMap::new();
+
+    for (index, cells) in rows.enumerate() {
+        // +1 for the header, +1 because Excel rows are 1-based
+        let row = first_row + index as u32 + 2;
+        let cell = |column: usize| cells.get(column).unwrap_or(&Data::Empty);
+
+        let student_number = cell_text(cell(columns.student_number));
+        let name = cell_text(cell(columns.name));
+        let points_cell = columns.points.map(cell).unwrap_or(&Data::Empty);
+
+        if student_number.is_empty() && name.is_empty() && matches!(points_cell, Data::Empty) {
+            continue;
+        }
+        total_rows += 1;
+
+        let mut row_valid = true;
+        if name.is_empty() {
+            errors.push(row_error(row, FIELD_NAME, name.clone(), "学生姓名不能为空"));
+            row_valid = false;
+        }
+
+        if student_number.is_empty() {
+            errors.push(row_error(row, FIELD_STUDENT_NUMBER, student_number.clone(), "学号不能为空"));
+            row_valid = false;
+        } else if !student_number.chars().all(|c| c.is_ascii_digit()) {
+            errors.push(row_error(row, FIELD_STUDENT_NUMBER, student_number.clone(), "学号必须为数字"));
+            row_valid = false;
+        } else if let Some(first) = seen_numbers.get(&student_number) {
+            errors.push(row_error(row, FIELD_STUDENT_NUMBER, student_number.clone(), &format!("学号与第 {} 行重复", first)));
+            row_valid = false;
+        } else {
+            seen_numbers.insert(student_number.clone(), row);
+        }
+
+        let points = cell_points(points_cell);
+        if points.is_err() {
+            errors.push(row_error(row, FIELD_POINTS, cell_text(points_cell), "积分必须为整数"));
+            row_valid = false;
+        }
+
+        if row_valid {
+            valid.push(StudentImportRow {
+                row,
+                student_number,
+                name,
+                points: points.unwrap_or_default(),
+            });
+        }
+    }
+
+    Ok(StudentRoster {
+        total_rows,
+        rows: valid,
+        errors,
+    })
+}
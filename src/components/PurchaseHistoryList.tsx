@@ -1,7 +1,8 @@
-import type { PaginatedPurchaseRecords } from '../types'
-import { CheckCircle2, Package, Truck } from 'lucide-react'
+import type { PaginatedPurchaseRecords, PurchaseRecord, ShippingStatus } from '../types'
+import { CheckCircle2, Package, PackageCheck, RotateCcw, Truck, XCircle } from 'lucide-react'
 import { useEffect, useState } from 'react'
-import { purchaseApi } from '../services/tauriApi'
+import { exportApi, getErrorMessage, purchaseApi } from '../services/tauriApi'
+import { Confirm, useConfirm } from './Confirm'
 import Pagination from './Pagination'
 import { ToastContainer, useToast } from './Toast'
 
@@ -24,7 +25,11 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
   const [currentPage, setCurrentPage] = useState(1)
   const [sourceFilter, setSourceFilter] = useState<'all' | '购买' | '抽奖'>('all')
   const [pageSize] = useState(10) // 每页显示10条记录
+  const [refundQuantities, setRefundQuantities] = useState<Record<string, number>>({})
+  const [selectedIds, setSelectedIds] = useState<string[]>([])
+  const [bulkShipping, setBulkShipping] = useState(false)
   const { toasts, removeToast, showError, showSuccess } = useToast()
+  const { confirmState, showConfirm, handleConfirm, handleCancel } = useConfirm()
 
   const loadRecords = async (page: number = currentPage) => {
     try {
@@ -32,6 +37,7 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
       const data = await purchaseApi.getByClassPaginated(classId, page, pageSize, sourceFilter)
       setPaginatedData(data)
       setCurrentPage(page)
+      setSelectedIds([])
     }
     catch (error) {
       console.error('Failed to load purchase records:', error)
@@ -62,9 +68,11 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
   const getStatusText = (status: string) => {
     switch (status) {
       case 'pending': return '待发货'
+      case 'ready': return '待领取'
       case 'shipped': return '已发货'
-      // 向后兼容：如果数据库中还有delivered状态的记录
-      case 'delivered': return '已发货'
+      case 'received': return '已领取'
+      case 'cancelled': return '已取消'
+      case 'refunded': return '已退款'
       default: return status
     }
   }
@@ -72,9 +80,11 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
   const getStatusIcon = (status: string) => {
     switch (status) {
       case 'pending': return <Package className="w-4 h-4" />
-      case 'shipped': return <CheckCircle2 className="w-4 h-4" />
-      // 向后兼容：如果数据库中还有delivered状态的记录
-      case 'delivered': return <CheckCircle2 className="w-4 h-4" />
+      case 'ready': return <PackageCheck className="w-4 h-4" />
+      case 'shipped': return <Truck className="w-4 h-4" />
+      case 'received': return <CheckCircle2 className="w-4 h-4" />
+      case 'cancelled': return <XCircle className="w-4 h-4" />
+      case 'refunded': return <RotateCcw className="w-4 h-4" />
       default: return <Package className="w-4 h-4" />
     }
   }
@@ -82,14 +92,52 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
   const getStatusColor = (status: string) => {
     switch (status) {
       case 'pending': return 'text-orange-600 bg-orange-50'
+      case 'ready': return 'text-blue-600 bg-blue-50'
       case 'shipped': return 'text-green-600 bg-green-50'
-      // 向后兼容：如果数据库中还有delivered状态的记录
-      case 'delivered': return 'text-green-600 bg-green-50'
+      case 'received': return 'text-emerald-700 bg-emerald-50'
+      case 'cancelled': return 'text-red-600 bg-red-50'
+      case 'refunded': return 'text-gray-500 bg-gray-100'
       default: return 'text-gray-600 bg-gray-50'
     }
   }
 
-  const handleUpdateStatus = async (recordId: string, newStatus: 'pending' | 'shipped') => {
+  // 与后端状态机一致：每个状态可执行的下一步操作
+  const getNextActions = (status: ShippingStatus): { status: Exclude<ShippingStatus, 'refunded'>, label: string, icon: typeof Truck }[] => {
+    switch (status) {
+      case 'pending': return [
+        { status: 'ready', label: '备货', icon: PackageCheck },
+        { status: 'shipped', label: '发货', icon: Truck },
+        { status: 'cancelled', label: '取消', icon: XCircle },
+      ]
+      case 'ready': return [
+        { status: 'shipped', label: '发货', icon: Truck },
+        { status: 'cancelled', label: '取消', icon: XCircle },
+      ]
+      case 'shipped': return [{ status: 'received', label: '确认领取', icon: CheckCircle2 }]
+      default: return []
+    }
+  }
+
+  // 取消会退还剩余件数的积分与库存，且不可恢复，因此需确认并重新加载
+  const handleCancelRecord = (record: PurchaseRecord) => {
+    showConfirm(`确定取消 ${record.studentName} 的 ${record.productName} 吗？将退还剩余 ${record.quantity - record.refundedQuantity} 件的积分并恢复库存，取消后不可恢复。`, async () => {
+      try {
+        setUpdating(record.id)
+        await purchaseApi.updateShippingStatus(record.id, 'cancelled')
+        await loadRecords(currentPage)
+        showSuccess('订单已取消，积分与库存已退还')
+      }
+      catch (error) {
+        console.error('Failed to cancel purchase record:', error)
+        showError(getErrorMessage(error, '取消失败，请重试'))
+      }
+      finally {
+        setUpdating(null)
+      }
+    }, '取消确认')
+  }
+
+  const handleUpdateStatus = async (recordId: string, newStatus: Exclude<ShippingStatus, 'refunded'>) => {
     // 保存原始状态用于回滚
     const originalRecord = paginatedData.records.find(record => record.id === recordId)
     if (!originalRecord)
@@ -116,7 +164,7 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
     }
     catch (error) {
       console.error('Failed to update shipping status:', error)
-      showError('更新发货状态失败，请重试')
+      showError(getErrorMessage(error, '更新发货状态失败，请重试'))
 
       // 回滚前端状态
       setPaginatedData(prev => ({
@@ -133,6 +181,76 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
     }
   }
 
+  const isShippable = (record: PurchaseRecord) => record.shippingStatus === 'pending' || record.shippingStatus === 'ready'
+
+  const toggleSelected = (recordId: string) => {
+    setSelectedIds(prev => prev.includes(recordId) ? prev.filter(id => id !== recordId) : [...prev, recordId])
+  }
+
+  const handleBulkShip = (scope: 'selected' | 'class') => {
+    const message = scope === 'selected'
+      ? `确定将选中的 ${selectedIds.length} 条记录标记为已发货吗？`
+      : `确定将 ${className} 所有待发货、待领取的记录标记为已发货吗？`
+
+    showConfirm(message, async () => {
+      try {
+        setBulkShipping(true)
+        const result = scope === 'selected'
+          ? await purchaseApi.shipRecords(selectedIds)
+          : await purchaseApi.shipClassPending(classId)
+        await loadRecords(currentPage)
+        showSuccess(result.updated > 0 ? `已发货 ${result.updated} 条记录` : '没有需要发货的记录')
+      }
+      catch (error) {
+        console.error('Failed to ship records:', error)
+        showError(getErrorMessage(error, '批量发货失败，请重试'))
+      }
+      finally {
+        setBulkShipping(false)
+      }
+    }, '批量发货')
+  }
+
+  const handleExportPickList = async () => {
+    try {
+      const result = await exportApi.pickList(classId, 'html')
+      showSuccess(result.rowCount > 0 ? `备货清单已保存: ${result.path}` : `没有待发货记录，已保存空清单: ${result.path}`)
+    }
+    catch (error) {
+      console.error('Failed to export pick list:', error)
+      showError(getErrorMessage(error, '导出备货清单失败，请重试'))
+    }
+  }
+
+  const handleRefund = (record: PurchaseRecord) => {
+    const remaining = record.quantity - record.refundedQuantity
+    const quantity = Math.min(refundQuantities[record.id] ?? remaining, remaining)
+
+    showConfirm(`确定为 ${record.studentName} 退款 ${record.productName} x${quantity} 吗？将退还对应积分并恢复库存。`, async () => {
+      try {
+        setUpdating(record.id)
+        const result = await purchaseApi.refund(record.id, quantity)
+        setPaginatedData(prev => ({
+          ...prev,
+          records: prev.records.map(item => item.id === record.id ? result.record : item),
+        }))
+        setRefundQuantities((prev) => {
+          const next = { ...prev }
+          delete next[record.id]
+          return next
+        })
+        showSuccess(`已退还 ${result.refundedPoints} 积分，${record.studentName} 当前积分 ${result.studentPoints}`)
+      }
+      catch (error) {
+        console.error('Failed to refund purchase record:', error)
+        showError(getErrorMessage(error, '退款失败，请重试'))
+      }
+      finally {
+        setUpdating(null)
+      }
+    }, '退款确认')
+  }
+
   if (loading) {
     return (
       <div className="min-h-[60vh] bg-gradient-to-br from-blue-50 via-white to-purple-50">
@@ -203,6 +321,30 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
                 {' '}
                 条
               </div>
+              <button
+                onClick={() => handleBulkShip('class')}
+                disabled={bulkShipping}
+                className="bg-white/20 hover:bg-white/30 text-white px-4 py-2 rounded-full text-sm font-bold shadow-lg transition-all duration-200 disabled:opacity-50"
+              >
+                🚚 全部发货
+              </button>
+              <button
+                onClick={handleExportPickList}
+                className="bg-white/20 hover:bg-white/30 text-white px-4 py-2 rounded-full text-sm font-bold shadow-lg transition-all duration-200"
+              >
+                📋 备货清单
+              </button>
+              {selectedIds.length > 0 && (
+                <button
+                  onClick={() => handleBulkShip('selected')}
+                  disabled={bulkShipping}
+                  className="bg-white text-blue-700 hover:bg-blue-50 px-4 py-2 rounded-full text-sm font-bold shadow-lg transition-all duration-200 disabled:opacity-50"
+                >
+                  发货所选（
+                  {selectedIds.length}
+                  ）
+                </button>
+              )}
               <div className="bg-white/20 rounded-full px-3 py-1">
                 <select
                   value={sourceFilter}
@@ -246,6 +388,15 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
                   <table className="min-w-full divide-y divide-gray-200">
                     <thead className="bg-gradient-to-r from-gray-50 to-gray-100">
                       <tr>
+                        <th className="pl-6 py-4 text-left">
+                          <input
+                            type="checkbox"
+                            aria-label="选择本页可发货记录"
+                            checked={paginatedData.records.some(isShippable) && paginatedData.records.filter(isShippable).every(record => selectedIds.includes(record.id))}
+                            onChange={event => setSelectedIds(event.target.checked ? paginatedData.records.filter(isShippable).map(record => record.id) : [])}
+                            disabled={!paginatedData.records.some(isShippable)}
+                          />
+                        </th>
                         <th className="px-6 py-4 text-left text-xs font-bold text-gray-600 uppercase tracking-wider">
                           商品信息
                         </th>
@@ -272,6 +423,16 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
                     <tbody className="bg-white divide-y divide-gray-200">
                       {paginatedData.records.map((record, index) => (
                         <tr key={record.id} className={`hover:bg-gray-50 transition-colors ${index % 2 === 0 ? 'bg-white' : 'bg-gray-50/30'}`}>
+                          <td className="pl-6 py-4">
+                            {isShippable(record) && (
+                              <input
+                                type="checkbox"
+                                aria-label="选择记录"
+                                checked={selectedIds.includes(record.id)}
+                                onChange={() => toggleSelected(record.id)}
+                              />
+                            )}
+                          </td>
                           <td className="px-6 py-4 whitespace-nowrap">
                             <div className="flex items-center">
                               <div>
@@ -293,6 +454,15 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
                               {' '}
                               积分
                             </div>
+                            {record.refundedQuantity > 0 && (
+                              <div className="text-xs text-gray-500">
+                                已退
+                                {' '}
+                                {record.refundedQuantity}
+                                {' '}
+                                件
+                              </div>
+                            )}
                           </td>
                           <td className="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
                             {new Date(record.createdAt).toLocaleString('zh-CN')}
@@ -315,23 +485,53 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
                           </td>
                           <td className="px-6 py-4 whitespace-nowrap text-sm font-medium">
                             <div className="flex space-x-2">
-                              {record.shippingStatus === 'pending' && (
+                              {getNextActions(record.shippingStatus).map(action => (
                                 <button
-                                  onClick={() => handleUpdateStatus(record.id, 'shipped')}
+                                  key={action.status}
+                                  onClick={() => action.status === 'cancelled' ? handleCancelRecord(record) : handleUpdateStatus(record.id, action.status)}
                                   disabled={updating === record.id}
-                                  className="inline-flex items-center px-3 py-1 rounded-md text-xs font-medium bg-blue-600 text-white hover:bg-blue-700 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-blue-500 disabled:opacity-50 disabled:cursor-not-allowed transition-colors"
+                                  className={`inline-flex items-center px-3 py-1 rounded-md text-xs font-medium focus:outline-none focus:ring-2 focus:ring-offset-2 disabled:opacity-50 disabled:cursor-not-allowed transition-colors ${
+                                    action.status === 'cancelled'
+                                      ? 'bg-white text-red-600 border border-red-200 hover:bg-red-50 focus:ring-red-400'
+                                      : 'bg-blue-600 text-white hover:bg-blue-700 focus:ring-blue-500'
+                                  }`}
                                 >
                                   {updating === record.id
                                     ? (
-                                        <div className="animate-spin rounded-full h-3 w-3 border border-white border-t-transparent mr-1"></div>
+                                        <div className="animate-spin rounded-full h-3 w-3 border border-current border-t-transparent mr-1"></div>
                                       )
                                     : (
-                                        <Truck className="w-3 h-3 mr-1" />
+                                        <action.icon className="w-3 h-3 mr-1" />
                                       )}
-                                  发货
+                                  {action.label}
+                                </button>
+                              ))}
+                              {record.shippingStatus !== 'refunded' && record.quantity - record.refundedQuantity > 1 && (
+                                <select
+                                  value={refundQuantities[record.id] ?? record.quantity - record.refundedQuantity}
+                                  onChange={event => setRefundQuantities(prev => ({ ...prev, [record.id]: Number(event.target.value) }))}
+                                  disabled={updating === record.id}
+                                  className="px-2 py-1 rounded-md text-xs border border-gray-300"
+                                >
+                                  {Array.from({ length: record.quantity - record.refundedQuantity }, (_, i) => i + 1).map(quantity => (
+                                    <option key={quantity} value={quantity}>
+                                      {quantity}
+                                      {' '}
+                                      件
+                                    </option>
+                                  ))}
+                                </select>
+                              )}
+                              {record.quantity - record.refundedQuantity > 0 && (
+                                <button
+                                  onClick={() => handleRefund(record)}
+                                  disabled={updating === record.id}
+                                  className="inline-flex items-center px-3 py-1 rounded-md text-xs font-medium bg-gray-100 text-gray-700 hover:bg-gray-200 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-gray-400 disabled:opacity-50 disabled:cursor-not-allowed transition-colors"
+                                >
+                                  <RotateCcw className="w-3 h-3 mr-1" />
+                                  退款
                                 </button>
                               )}
-                              {/* 已发货状态不显示任何按钮，流程结束 */}
                             </div>
                           </td>
                         </tr>
@@ -357,6 +557,14 @@ export default function PurchaseHistoryList({ classId, className, onBackToShop }
         toasts={toasts}
         onRemoveToast={removeToast}
       />
+
+      <Confirm
+        isOpen={confirmState.isOpen}
+        title={confirmState.title}
+        message={confirmState.message}
+        onConfirm={handleConfirm}
+        onCancel={handleCancel}
+      />
     </div>
   )
 }
@@ -2,7 +2,7 @@ import type { WheelDataType } from 'react-custom-roulette'
 import type { SpinWheelResult, Student, WheelConfig } from '../types'
 import { useEffect, useMemo, useState } from 'react'
 import { Wheel } from 'react-custom-roulette'
-import { studentApi, wheelApi } from '../services/tauriApi'
+import { getErrorMessage, studentApi, wheelApi } from '../services/tauriApi'
 
 interface SpinWheelPanelProps {
   classId: string
@@ -125,7 +125,7 @@ export default function SpinWheelPanel({
     }
     catch (error) {
       console.error('Failed to spin wheel:', error)
-      showError(typeof error === 'string' ? error : '开启转盘失败，请重试')
+      showError(getErrorMessage(error, '开启转盘失败，请重试'))
       setSpinning(false)
     }
   }
@@ -51,7 +51,18 @@ export default function ClassManagement() {
   }
 
   const handleDelete = async (id: string) => {
-    showConfirm('确定要删除这个班级吗？删除后该班级下的所有学生信息也将被删除。', async () => {
+    let message = '确定要删除这个班级吗？班级及其学生、商品将移入回收站，可随时恢复。'
+    try {
+      const preview = await classApi.previewDeletion(id)
+      message = `确定要删除班级「${preview.className}」吗？将一并移入回收站：学生 ${preview.students} 名、商品 ${preview.products} 件，关联购买记录 ${preview.purchaseRecords} 条、转盘格子 ${preview.wheelSlots} 个，可随时恢复。`
+      if (preview.trashedStudents > 0 || preview.trashedProducts > 0)
+        message += `回收站中另有该班学生 ${preview.trashedStudents} 名、商品 ${preview.trashedProducts} 件，清空回收站时将随班级一并永久删除。`
+    }
+    catch (error) {
+      console.error('Failed to preview class deletion:', error)
+    }
+
+    showConfirm(message, async () => {
       try {
         await classApi.delete(id)
         await loadClasses()
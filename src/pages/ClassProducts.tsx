@@ -5,7 +5,7 @@ import * as XLSX from 'xlsx'
 import { Confirm, useConfirm } from '../components/Confirm'
 import { ToastContainer, useToast } from '../components/Toast'
 import WheelConfigPanel from '../components/WheelConfigPanel'
-import { classApi, fileApi, productApi } from '../services/tauriApi'
+import { classApi, fileApi, isAppError, productApi } from '../services/tauriApi'
 
 interface ProductModalProps {
   isOpen: boolean
@@ -184,17 +184,23 @@ export default function ClassProducts() {
   }
 
   const handleDelete = async (id: string) => {
-    showConfirm('确定要删除这个商品吗？删除后将无法恢复。', async () => {
+    const deleteProduct = async (force: boolean) => {
       try {
-        await productApi.delete(id)
+        await productApi.delete(id, force)
         await loadData()
         showSuccess('商品删除成功')
       }
       catch (error) {
+        if (!force && isAppError(error, 'CONSTRAINT_VIOLATION')) {
+          showConfirm(error.message, () => deleteProduct(true), '商品仍被引用')
+          return
+        }
         console.error('Failed to delete product:', error)
         showError('删除失败，请重试')
       }
-    }, '删除确认')
+    }
+
+    showConfirm('确定要删除这个商品吗？删除后可在回收站中恢复。', () => deleteProduct(false), '删除确认')
   }
 
   const handleSubmit = async (data: { name: string, points: number, stock: number }) => {
@@ -328,10 +334,9 @@ export default function ClassProducts() {
       const excelBuffer = XLSX.write(workbook, { bookType: 'xlsx', type: 'array' })
       const uint8Array = new Uint8Array(excelBuffer)
 
-      // 保存到桌面
-      await fileApi.saveToDesktop('商品导入模板.xlsx', uint8Array)
+      const saved = await fileApi.save('商品导入模板.xlsx', uint8Array)
 
-      showSuccess('模板已下载到桌面: 商品导入模板.xlsx')
+      showSuccess(`模板已保存: ${saved.path}`)
     }
     catch (error) {
       console.error('Failed to download template:', error)
@@ -1,4 +1,4 @@
-import type { Class, Student } from '../types'
+import type { Class, Student, StudentImportError } from '../types'
 import { useEffect, useState } from 'react'
 import { useNavigate, useParams } from 'react-router-dom'
 import * as XLSX from 'xlsx'
@@ -7,7 +7,7 @@ import FallingAnimation from '../components/FallingAnimation'
 import RandomCallModal from '../components/RandomCallModal'
 import SimpleStudentModal from '../components/SimpleStudentModal'
 import { ToastContainer, useToast } from '../components/Toast'
-import { classApi, fileApi, studentApi } from '../services/tauriApi'
+import { classApi, exportApi, fileApi, getErrorMessage, studentApi } from '../services/tauriApi'
 
 export default function ClassStudents() {
   const { classId } = useParams<{ classId: string }>()
@@ -71,7 +71,7 @@ export default function ClassStudents() {
   }
 
   const handleDelete = async (id: string) => {
-    showConfirm('确定要删除这个学生吗？删除后将无法恢复。', async () => {
+    showConfirm('确定要删除这个学生吗？删除后可在回收站中恢复。', async () => {
       try {
         await studentApi.delete(id)
         await loadData()
@@ -93,7 +93,11 @@ export default function ClassStudents() {
       }
 
       if (editingStudent) {
-        await studentApi.update(editingStudent.id, studentData)
+        await studentApi.update(editingStudent.id, { name: data.name, studentNumber: data.studentNumber })
+        // 积分按打开弹窗时的差值调整，不覆盖期间其他加减分
+        const delta = data.points - editingStudent.points
+        if (delta !== 0)
+          await studentApi.adjustPoints(editingStudent.id, delta, '编辑学生积分')
         showSuccess('学生修改成功')
       }
       else {
@@ -110,8 +114,6 @@ export default function ClassStudents() {
   }
 
   const handlePointsChange = async (student: Student, delta: number) => {
-    const newPoints = student.points + delta
-
     // 只有加分时才触发扩散动画
     if (delta > 0) {
       setAnimations(prev => ({
@@ -133,22 +135,35 @@ export default function ClassStudents() {
 
     // 立即更新前端状态
     setStudents(prev => prev.map(s =>
-      s.id === student.id ? { ...s, points: newPoints } : s,
+      s.id === student.id ? { ...s, points: s.points + delta } : s,
     ))
 
     try {
-      await studentApi.update(student.id, { points: newPoints })
+      const result = await studentApi.adjustPoints(student.id, delta)
+      // 以后端返回的余额为准
+      setStudents(prev => prev.map(s =>
+        s.id === student.id ? { ...s, points: result.balance } : s,
+      ))
     }
     catch (error) {
       console.error('Failed to update points:', error)
-      // 失败时回滚到原来的积分
+      // 失败时回滚本次变动
       setStudents(prev => prev.map(s =>
-        s.id === student.id ? { ...s, points: student.points } : s,
+        s.id === student.id ? { ...s, points: s.points - delta } : s,
       ))
-      showError('积分更新失败，请重试')
+      showError(getErrorMessage(error, '积分更新失败，请重试'))
     }
   }
 
+  // 按行汇总错误，只展示前几条
+  const describeImportErrors = (errors: StudentImportError[]) => {
+    const details = errors
+      .slice(0, 3)
+      .map(error => `第${error.row}行：${error.message}`)
+      .join('；')
+    return errors.length > 3 ? `${details} 等 ${errors.length} 处问题` : details
+  }
+
   const handleImportExcel = async (event: React.ChangeEvent<HTMLInputElement>) => {
     const file = event.target.files?.[0]
     if (!file)
@@ -166,52 +181,39 @@ export default function ClassStudents() {
       reader.onload = async (e) => {
         try {
           const data = new Uint8Array(e.target?.result as ArrayBuffer)
-          const workbook = XLSX.read(data, { type: 'array' })
-          const sheetName = workbook.SheetNames[0]
-          const worksheet = workbook.Sheets[sheetName]
-
-          // 转换为JSON，跳过第一行标题
-          const jsonData = XLSX.utils.sheet_to_json(worksheet, { header: 1 }) as any[][]
+          const preview = await studentApi.previewImport(classId!, data)
 
-          if (jsonData.length < 2) {
-            showError('Excel文件内容为空或格式错误')
-            setImportLoading(false)
+          if (preview.createCount === 0 && preview.updateCount === 0) {
+            showError(preview.errors.length > 0
+              ? `没有可导入的学生，${describeImportErrors(preview.errors)}`
+              : '名单与当前班级一致，无需导入')
             return
           }
 
-          // 从第二行开始处理数据
-          const studentsToImport = jsonData.slice(1).filter(row => row[0] || row[1]).map(row => ({
-            name: String(row[1] || '').trim(),
-            studentNumber: String(row[0] || '').trim(),
-            points: row[2] === undefined || row[2] === null || row[2] === '' ? 0 : (Number(row[2]) || 0),
-            classId: classId!,
-            className: classInfo!.name,
-          })).filter(student => student.name)
-
-          if (studentsToImport.length === 0) {
-            showError('未找到有效的学生数据')
-            setImportLoading(false)
-            return
-          }
+          // 先展示预览，确认后再按学号新增/更新
+          const summary = [
+            `新增 ${preview.createCount} 人`,
+            `更新 ${preview.updateCount} 人`,
+            `无变化 ${preview.unchangedCount} 人`,
+            preview.conflictCount > 0 ? `冲突 ${preview.conflictCount} 人（跳过）` : '',
+            preview.errors.length > 0 ? `${describeImportErrors(preview.errors)}（跳过）` : '',
+          ].filter(Boolean).join('，')
 
-          // 批量创建学生
-          let successCount = 0
-          for (const studentData of studentsToImport) {
+          showConfirm(`${summary}。确定导入吗？`, async () => {
             try {
-              await studentApi.create(studentData)
-              successCount++
+              const result = await studentApi.commitImport(classId!, preview.rows)
+              await loadData()
+              showSuccess(`导入完成：新增 ${result.created} 人，更新 ${result.updated} 人`)
             }
             catch (error) {
-              console.error(`Failed to create student ${studentData.name}:`, error)
+              console.error('Failed to commit import:', error)
+              showError(getErrorMessage(error, '导入失败，请重试'))
             }
-          }
-
-          await loadData()
-          showSuccess(`成功导入 ${successCount} 个学生`)
+          }, '确认导入名单')
         }
         catch (error) {
-          console.error('Failed to parse Excel:', error)
-          showError('Excel文件解析失败，请检查文件格式')
+          console.error('Failed to import Excel:', error)
+          showError(getErrorMessage(error, 'Excel文件解析失败，请检查文件格式'))
         }
         finally {
           setImportLoading(false)
@@ -236,41 +238,12 @@ export default function ClassStudents() {
     }
 
     try {
-      // 准备导出数据
-      const exportData = [
-        ['学号', '学生姓名', '积分'], // 标题行
-        ...students.map(student => [student.studentNumber, student.name, student.points]),
-      ]
-
-      // 创建工作簿
-      const worksheet = XLSX.utils.aoa_to_sheet(exportData)
-      const workbook = XLSX.utils.book_new()
-
-      // 设置列宽
-      worksheet['!cols'] = [
-        { wch: 12 }, // 学号列宽
-        { wch: 15 }, // 学生姓名列宽
-        { wch: 10 }, // 积分列宽
-      ]
-
-      // 添加工作表
-      XLSX.utils.book_append_sheet(workbook, worksheet, '学生列表')
-
-      // 生成文件名
-      const fileName = `${classInfo?.name || '班级'}_学生名单_${new Date().toLocaleDateString().replace(/\//g, '-')}.xlsx`
-
-      // 生成二进制数据
-      const excelBuffer = XLSX.write(workbook, { bookType: 'xlsx', type: 'array' })
-      const uint8Array = new Uint8Array(excelBuffer)
-
-      // 保存到桌面
-      await fileApi.saveToDesktop(fileName, uint8Array)
-
-      showSuccess(`文件已保存到桌面: ${fileName}`)
+      const result = await exportApi.students(classId!, 'xlsx')
+      showSuccess(`文件已保存: ${result.path}`)
     }
     catch (error) {
       console.error('Failed to export Excel:', error)
-      showError('导出失败，请重试')
+      showError(getErrorMessage(error, '导出失败，请重试'))
     }
   }
 
@@ -302,10 +275,9 @@ export default function ClassStudents() {
       const excelBuffer = XLSX.write(workbook, { bookType: 'xlsx', type: 'array' })
       const uint8Array = new Uint8Array(excelBuffer)
 
-      // 保存到桌面
-      await fileApi.saveToDesktop('学生导入模板.xlsx', uint8Array)
+      const saved = await fileApi.save('学生导入模板.xlsx', uint8Array)
 
-      showSuccess('模板已下载到桌面: 学生导入模板.xlsx')
+      showSuccess(`模板已保存: ${saved.path}`)
     }
     catch (error) {
       console.error('Failed to download template:', error)
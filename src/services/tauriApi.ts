@@ -1,6 +1,17 @@
-import type { Class, Product, PurchaseRecord, PaginatedPurchaseRecords, Student, WheelConfig, SpinWheelResult } from '../types'
+import type { Class, Product, PurchaseRecord, PaginatedPurchaseRecords, Student, WheelConfig, SpinWheelResult, PointTransaction, PaginatedPointTransactions, PointAdjustmentResult, PointSettings, BatchAdjustPointsResult, PointReason, PaginatedAuditLogs, TrashItem, PurgeTrashResult, ClassDeletionPreview, ArchivedTerm, ArchivedTermDetail, RolloverBalancePolicy, TransferHistoryPolicy, TransferStudentResult, StudentTransfer, IntegrityReport, RepairIntegrityResult, BackupInfo, BackupSettings, RestoreBackupResult, SchemaInfo, DataLocation, DetectedDatabase, ClassBundle, ClassBundleImportMode, ClassBundleImportPreview, ImportClassBundleResult, StudentImportReport, StudentImportPreview, StudentImportPlanRow, CommitStudentImportResult, ExportFormat, ExportFileResult, PurchaseRecordExportFilter, SavedFile, RefundPurchaseResult, ShippingStatus, BulkShippingResult, PickList, AppError, ErrorCode } from '../types'
 import { invoke } from '@tauri-apps/api/core'
 
+// Error helpers
+export function isAppError(error: unknown, code?: ErrorCode): error is AppError {
+  if (typeof error !== 'object' || error === null || !('code' in error) || !('message' in error))
+    return false
+  return code === undefined || (error as AppError).code === code
+}
+
+export function getErrorMessage(error: unknown, fallback: string): string {
+  return isAppError(error) ? error.message : fallback
+}
+
 // Class API
 export const classApi = {
   async getAll(): Promise<Class[]> {
@@ -29,6 +40,10 @@ export const classApi = {
   async delete(id: string): Promise<void> {
     return await invoke('delete_class', { id })
   },
+
+  async previewDeletion(id: string): Promise<ClassDeletionPreview> {
+    return await invoke('preview_class_deletion', { id })
+  },
 }
 
 // Student API
@@ -52,15 +67,34 @@ export const studentApi = {
     })
   },
 
-  async update(id: string, studentData: Partial<Omit<Student, 'id'>>): Promise<Student> {
+  async importXlsx(classId: string, bytes: Uint8Array): Promise<StudentImportReport> {
+    return await invoke('import_students_xlsx', {
+      classId,
+      bytes: Array.from(bytes),
+    })
+  },
+
+  async previewImport(classId: string, bytes: Uint8Array): Promise<StudentImportPreview> {
+    return await invoke('preview_student_import', {
+      classId,
+      bytes: Array.from(bytes),
+    })
+  },
+
+  async commitImport(classId: string, rows: StudentImportPlanRow[]): Promise<CommitStudentImportResult> {
+    return await invoke('commit_student_import', {
+      classId,
+      request: { rows },
+    })
+  },
+
+  // 积分不在此修改，请使用 adjustPoints
+  async update(id: string, studentData: Partial<Omit<Student, 'id' | 'points'>>): Promise<Student> {
     const request: any = {}
 
     if (studentData.name !== undefined) {
       request.name = studentData.name
     }
-    if (studentData.points !== undefined) {
-      request.points = studentData.points
-    }
     if (studentData.classId !== undefined) {
       request.class_id = studentData.classId
     }
@@ -74,6 +108,103 @@ export const studentApi = {
   async delete(id: string): Promise<void> {
     return await invoke('delete_student', { id })
   },
+
+  async transfer(id: string, toClassId: string, historyPolicy?: TransferHistoryPolicy): Promise<TransferStudentResult> {
+    return await invoke('transfer_student', {
+      id,
+      request: {
+        to_class_id: toClassId,
+        history_policy: historyPolicy,
+      },
+    })
+  },
+
+  async getTransfers(studentId: string): Promise<StudentTransfer[]> {
+    return await invoke('get_student_transfers', { studentId })
+  },
+
+  async adjustPoints(studentId: string, delta: number, reason?: string, reasonId?: string): Promise<PointAdjustmentResult> {
+    return await invoke('adjust_student_points', { studentId, delta, reason, reasonId })
+  },
+
+  async batchAdjustPoints(target: { studentIds?: string[], classId?: string }, delta: number, reason?: string, reasonId?: string): Promise<BatchAdjustPointsResult> {
+    return await invoke('batch_adjust_points', {
+      request: {
+        student_ids: target.studentIds,
+        class_id: target.classId,
+        delta,
+        reason,
+        reason_id: reasonId,
+      },
+    })
+  },
+}
+
+// Point reason API
+export const pointReasonApi = {
+  async getByClass(classId: string): Promise<PointReason[]> {
+    return await invoke('get_point_reasons_by_class', { classId })
+  },
+
+  async create(reason: Omit<PointReason, 'id' | 'createdAt' | 'category' | 'sortOrder'> & Partial<Pick<PointReason, 'category' | 'sortOrder'>>): Promise<PointReason> {
+    return await invoke('create_point_reason', {
+      request: {
+        class_id: reason.classId,
+        label: reason.label,
+        default_delta: reason.defaultDelta,
+        category: reason.category,
+        sort_order: reason.sortOrder,
+      },
+    })
+  },
+
+  async update(id: string, reason: Partial<Omit<PointReason, 'id' | 'classId' | 'createdAt'>>): Promise<PointReason> {
+    return await invoke('update_point_reason', {
+      id,
+      request: {
+        label: reason.label,
+        default_delta: reason.defaultDelta,
+        category: reason.category,
+        sort_order: reason.sortOrder,
+      },
+    })
+  },
+
+  async delete(id: string): Promise<void> {
+    return await invoke('delete_point_reason', { id })
+  },
+}
+
+// Point ledger API
+export const pointApi = {
+  async getStudentTransactions(studentId: string): Promise<PointTransaction[]> {
+    return await invoke('get_student_point_transactions', { studentId })
+  },
+
+  async getByClassPaginated(classId: string, page: number, pageSize: number, studentId?: string): Promise<PaginatedPointTransactions> {
+    return await invoke('get_point_transactions_paginated', {
+      classId,
+      page,
+      pageSize,
+      studentId,
+    })
+  },
+
+  async undo(transactionId: string): Promise<PointTransaction[]> {
+    return await invoke('undo_point_operation', { transactionId })
+  },
+
+  async undoLast(classId: string, count: number): Promise<PointTransaction[]> {
+    return await invoke('undo_last_point_operations', { classId, count })
+  },
+
+  async getSettings(): Promise<PointSettings> {
+    return await invoke('get_point_settings')
+  },
+
+  async updateSettings(settings: PointSettings): Promise<PointSettings> {
+    return await invoke('update_point_settings', { request: settings })
+  },
 }
 
 // Product API
@@ -109,8 +240,8 @@ export const productApi = {
     return await invoke('update_product', { id, request })
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_product', { id })
+  async delete(id: string, force?: boolean): Promise<void> {
+    return await invoke('delete_product', { id, force })
   },
 }
 
@@ -139,7 +270,7 @@ export const purchaseApi = {
     })
   },
 
-  async updateShippingStatus(recordId: string, status: 'pending' | 'shipped'): Promise<void> {
+  async updateShippingStatus(recordId: string, status: Exclude<ShippingStatus, 'refunded'>): Promise<void> {
     return await invoke('update_shipping_status', {
       recordId,
       request: {
@@ -147,6 +278,35 @@ export const purchaseApi = {
       },
     })
   },
+
+  // 批量发货：单个事务，任一记录无法发货时全部不修改
+  async shipRecords(recordIds: string[]): Promise<BulkShippingResult> {
+    return await invoke('ship_purchase_records', { recordIds })
+  },
+
+  async shipStudentPending(studentId: string): Promise<BulkShippingResult> {
+    return await invoke('ship_student_pending_records', { studentId })
+  },
+
+  async shipClassPending(classId: string): Promise<BulkShippingResult> {
+    return await invoke('ship_class_pending_records', { classId })
+  },
+
+  // 不传 classId 时汇总全部班级
+  async getPickList(classId?: string): Promise<PickList> {
+    return await invoke('get_pick_list', { classId })
+  },
+
+  // quantity 省略时退还全部未退款数量
+  async refund(recordId: string, quantity?: number, reason?: string): Promise<RefundPurchaseResult> {
+    return await invoke('refund_purchase_record', {
+      recordId,
+      request: {
+        quantity,
+        reason,
+      },
+    })
+  },
 }
 
 // Wheel API
@@ -175,12 +335,173 @@ export const wheelApi = {
   },
 }
 
+// Audit log API
+export const auditApi = {
+  async getPaginated(params: { page: number, pageSize: number, classId?: string, entityType?: string, entityId?: string }): Promise<PaginatedAuditLogs> {
+    return await invoke('get_audit_logs_paginated', params)
+  },
+}
+
+// Semester rollover API
+export const termApi = {
+  async rollover(classId: string, termName: string, balancePolicy: RolloverBalancePolicy, carryPercent?: number): Promise<ArchivedTerm> {
+    return await invoke('rollover_class', {
+      classId,
+      request: {
+        term_name: termName,
+        balance_policy: balancePolicy,
+        carry_percent: carryPercent,
+      },
+    })
+  },
+
+  async getByClass(classId: string): Promise<ArchivedTerm[]> {
+    return await invoke('get_archived_terms_by_class', { classId })
+  },
+
+  async getDetail(termId: string): Promise<ArchivedTermDetail> {
+    return await invoke('get_archived_term_detail', { termId })
+  },
+}
+
+// Integrity API
+export const integrityApi = {
+  async check(): Promise<IntegrityReport> {
+    return await invoke('check_integrity')
+  },
+
+  async repair(): Promise<RepairIntegrityResult> {
+    return await invoke('repair_integrity')
+  },
+}
+
+// Backup API
+export const backupApi = {
+  async getSchemaInfo(): Promise<SchemaInfo> {
+    return await invoke('get_schema_info')
+  },
+
+  async create(): Promise<BackupInfo> {
+    return await invoke('create_backup')
+  },
+
+  async list(): Promise<BackupInfo[]> {
+    return await invoke('list_backups')
+  },
+
+  async restore(fileName: string): Promise<RestoreBackupResult> {
+    return await invoke('restore_backup', { fileName })
+  },
+
+  async getSettings(): Promise<BackupSettings> {
+    return await invoke('get_backup_settings')
+  },
+
+  async updateSettings(settings: BackupSettings): Promise<BackupSettings> {
+    return await invoke('update_backup_settings', { settings })
+  },
+}
+
+// Data location API
+export const dataLocationApi = {
+  async get(): Promise<DataLocation> {
+    return await invoke('get_data_location')
+  },
+
+  async move(targetDir: string): Promise<DataLocation> {
+    return await invoke('move_data_location', { targetDir })
+  },
+
+  async detectLegacy(): Promise<DetectedDatabase[]> {
+    return await invoke('detect_legacy_databases')
+  },
+}
+
+// Class bundle API
+export const classBundleApi = {
+  async export(classId: string): Promise<ClassBundle> {
+    return await invoke('export_class_bundle', { classId })
+  },
+
+  async previewImport(bundle: ClassBundle): Promise<ClassBundleImportPreview> {
+    return await invoke('preview_class_bundle_import', { bundle })
+  },
+
+  async import(bundle: ClassBundle, mode: ClassBundleImportMode, targetClassId?: string): Promise<ImportClassBundleResult> {
+    return await invoke('import_class_bundle', {
+      request: {
+        bundle,
+        mode,
+        target_class_id: targetClassId,
+      },
+    })
+  },
+}
+
+// Recycle bin API
+export const trashApi = {
+  async list(): Promise<TrashItem[]> {
+    return await invoke('list_trash')
+  },
+
+  async restoreClass(id: string): Promise<Class> {
+    return await invoke('restore_class', { id })
+  },
+
+  async restoreStudent(id: string): Promise<Student> {
+    return await invoke('restore_student', { id })
+  },
+
+  async restoreProduct(id: string): Promise<Product> {
+    return await invoke('restore_product', { id })
+  },
+
+  async purge(olderThanDays?: number): Promise<PurgeTrashResult> {
+    return await invoke('purge_trash', { olderThanDays })
+  },
+}
+
 // File operations
 export const fileApi = {
-  async saveToDesktop(filename: string, data: Uint8Array): Promise<string> {
-    return await invoke('save_file_to_desktop', {
-      filename,
+  // 不传 targetDir 时保存到上次使用的文件夹（默认桌面，其次文档）；同名文件自动追加 (1)、(2)…
+  async save(fileName: string, data: Uint8Array, targetDir?: string): Promise<SavedFile> {
+    return await invoke('save_file', {
+      fileName,
       data: Array.from(data),
+      targetDir,
     })
   },
+
+  async getSaveLocation(): Promise<string> {
+    return await invoke('get_save_location')
+  },
+}
+
+// Export API：文件由后端生成并直接写入磁盘
+export const exportApi = {
+  async students(classId: string, format: ExportFormat, targetDir?: string): Promise<ExportFileResult> {
+    return await invoke('export_students', { classId, format, targetDir })
+  },
+
+  async products(classId: string, format: ExportFormat, targetDir?: string): Promise<ExportFileResult> {
+    return await invoke('export_products', { classId, format, targetDir })
+  },
+
+  async purchaseRecords(classId: string, filter: PurchaseRecordExportFilter, format: ExportFormat, targetDir?: string): Promise<ExportFileResult> {
+    return await invoke('export_purchase_records', {
+      classId,
+      filter: {
+        start_date: filter.startDate,
+        end_date: filter.endDate,
+        source: filter.source,
+        shipping_status: filter.shippingStatus,
+      },
+      format,
+      targetDir,
+    })
+  },
+
+  async pickList(classId: string | undefined, format: ExportFormat, targetDir?: string): Promise<ExportFileResult> {
+    return await invoke('export_pick_list', { classId, format, targetDir })
+  },
 }
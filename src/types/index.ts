@@ -16,6 +16,58 @@ export interface Student {
   createdAt: string
 }
 
+export interface PointTransaction {
+  id: string
+  studentId: string
+  classId: string
+  delta: number
+  balanceAfter: number
+  reason?: string
+  source: 'manual' | 'purchase' | 'wheel' | 'undo' | 'rollover' | 'import' | 'refund' | 'opening'
+  relatedId?: string
+  reasonId?: string
+  revertsId?: string
+  revertedBy?: string
+  operationId?: string
+  createdAt: string
+}
+
+export interface PaginatedPointTransactions {
+  transactions: PointTransaction[]
+  total: number
+  totalPages: number
+  currentPage: number
+  pageSize: number
+}
+
+export interface PointAdjustmentResult {
+  studentId: string
+  studentName: string
+  delta: number
+  balance: number
+  transactionId: string
+}
+
+export interface BatchAdjustPointsResult {
+  operationId: string
+  results: PointAdjustmentResult[]
+  totalStudents: number
+}
+
+export interface PointReason {
+  id: string
+  classId: string
+  label: string
+  defaultDelta: number
+  category: 'positive' | 'negative'
+  sortOrder: number
+  createdAt: string
+}
+
+export interface PointSettings {
+  minPoints: number
+}
+
 export interface Product {
   id: string
   name: string
@@ -35,6 +87,9 @@ export interface CartItem {
   quantity: number
 }
 
+// pending → ready → shipped → received；发货前可取消，refunded 仅由退款产生
+export type ShippingStatus = 'pending' | 'ready' | 'shipped' | 'received' | 'cancelled' | 'refunded'
+
 export interface PurchaseRecord {
   id: string
   productId: string
@@ -45,8 +100,50 @@ export interface PurchaseRecord {
   quantity: number
   classId: string
   createdAt: string
-  shippingStatus: 'pending' | 'shipped'
+  shippingStatus: ShippingStatus
   source: '购买' | '抽奖'
+  refundedQuantity: number
+  shippedAt?: string
+  receivedAt?: string
+}
+
+export interface PickListStudent {
+  studentId: string
+  studentName: string
+  studentNumber?: string
+  quantity: number
+  recordCount: number
+}
+
+export interface PickListProduct {
+  productId: string
+  productName: string
+  classId: string
+  className: string
+  totalQuantity: number
+  students: PickListStudent[]
+}
+
+// 待发货记录按商品、学生汇总的备货清单；classId 为空表示全部班级
+export interface PickList {
+  classId?: string
+  generatedAt: string
+  totalQuantity: number
+  recordCount: number
+  products: PickListProduct[]
+}
+
+// skipped：列表中本来就已发货的记录数
+export interface BulkShippingResult {
+  updated: number
+  skipped: number
+}
+
+export interface RefundPurchaseResult {
+  record: PurchaseRecord
+  refundedQuantity: number
+  refundedPoints: number
+  studentPoints: number
 }
 
 export interface PaginatedPurchaseRecords {
@@ -83,3 +180,287 @@ export interface SpinWheelResult {
   studentName: string
   record: PurchaseRecord
 }
+
+export interface AuditLog {
+  id: string
+  entityType: 'class' | 'student' | 'product' | 'purchase_record' | 'wheel_config' | 'point_reason' | 'setting'
+  entityId: string
+  classId?: string
+  action: string
+  before?: Record<string, unknown>
+  after?: Record<string, unknown>
+  createdAt: string
+}
+
+export interface PaginatedAuditLogs {
+  logs: AuditLog[]
+  total: number
+  totalPages: number
+  currentPage: number
+  pageSize: number
+}
+
+export interface ClassDeletionPreview {
+  classId: string
+  className: string
+  students: number
+  products: number
+  trashedStudents: number
+  trashedProducts: number
+  purchaseRecords: number
+  wheelSlots: number
+}
+
+export type TransferHistoryPolicy = 'carry' | 'split'
+
+export interface StudentTransfer {
+  id: string
+  studentId: string
+  fromClassId: string
+  toClassId: string
+  historyPolicy: TransferHistoryPolicy
+  purchaseRecordsMoved: number
+  pointTransactionsMoved: number
+  createdAt: string
+}
+
+export interface TransferStudentResult {
+  student: Student
+  transfer: StudentTransfer
+}
+
+export type RolloverBalancePolicy = 'reset' | 'carry' | 'percent'
+
+export interface ArchivedTerm {
+  id: string
+  classId: string
+  name: string
+  balancePolicy: RolloverBalancePolicy
+  carryPercent?: number
+  studentCount: number
+  totalPoints: number
+  purchaseCount: number
+  wheelConfig?: { spinCost: number, productIds: string[] }
+  periodStart?: string
+  archivedAt: string
+}
+
+export interface TermStudentBalance {
+  studentId: string
+  studentName: string
+  studentNumber: string
+  finalPoints: number
+  carriedPoints: number
+}
+
+export interface ArchivedTermDetail {
+  term: ArchivedTerm
+  balances: TermStudentBalance[]
+  purchaseRecords: PurchaseRecord[]
+}
+
+export interface IntegrityIssue {
+  code: string
+  description: string
+  count: number
+  sampleIds: string[]
+  repairable: boolean
+}
+
+export interface IntegrityReport {
+  healthy: boolean
+  issues: IntegrityIssue[]
+  checkedAt: string
+}
+
+export interface RepairIntegrityResult {
+  repairs: { code: string, fixed: number }[]
+  report: IntegrityReport
+}
+
+export interface SchemaInfo {
+  version: number
+  latestVersion: number
+  migrations: { version: number, name: string, appliedAt: string }[]
+}
+
+export interface BackupInfo {
+  fileName: string
+  kind: string
+  sizeBytes: number
+  schemaVersion?: number
+  createdAt: string
+}
+
+export interface BackupSettings {
+  retentionCount: number
+}
+
+export interface RestoreBackupResult {
+  restored: BackupInfo
+  safetyBackup: BackupInfo
+  schemaInfo: SchemaInfo
+}
+
+export interface DataLocation {
+  dataDir: string
+  dbPath: string
+  settingsPath: string
+  sizeBytes: number
+}
+
+export interface DetectedDatabase {
+  dataDir: string
+  dbPath: string
+  sizeBytes: number
+  schemaVersion?: number
+  modifiedAt?: string
+}
+
+export interface BundleWheelConfig {
+  spinCost: number
+  productIds: string[]
+}
+
+export interface ClassBundle {
+  format: 'pointhub-class-bundle'
+  version: number
+  exportedAt: string
+  schemaVersion: number
+  class: Class
+  students: Student[]
+  archivedStudents: Student[]
+  products: Product[]
+  archivedProducts: Product[]
+  purchaseRecords: PurchaseRecord[]
+  pointReasons: PointReason[]
+  wheelConfig?: BundleWheelConfig
+}
+
+export type ClassBundleImportMode = 'new' | 'merge'
+
+export interface ClassBundleImportPreview {
+  className: string
+  students: number
+  products: number
+  purchaseRecords: number
+  hasWheelConfig: boolean
+  collisions: Class[]
+}
+
+export interface ImportClassBundleResult {
+  class: Class
+  studentsCreated: number
+  studentsMerged: number
+  productsCreated: number
+  productsMerged: number
+  purchaseRecordsImported: number
+  purchaseRecordsSkipped: number
+  wheelConfigImported: boolean
+}
+
+export interface StudentImportError {
+  row: number
+  field: 'studentNumber' | 'name' | 'points'
+  value: string
+  message: string
+}
+
+export interface StudentImportReport {
+  totalRows: number
+  importedCount: number
+  students: Student[]
+  errors: StudentImportError[]
+}
+
+export interface StudentFieldChange {
+  field: 'name' | 'points'
+  before: string
+  after: string
+}
+
+export type StudentImportAction = 'create' | 'update' | 'unchanged' | 'conflict'
+
+export interface StudentImportPlanRow {
+  row: number
+  action: StudentImportAction
+  studentNumber: string
+  name: string
+  points?: number
+  studentId?: string
+  changes: StudentFieldChange[]
+  message?: string
+}
+
+export interface StudentImportPreview {
+  totalRows: number
+  rows: StudentImportPlanRow[]
+  errors: StudentImportError[]
+  createCount: number
+  updateCount: number
+  unchangedCount: number
+  conflictCount: number
+}
+
+export interface CommitStudentImportResult {
+  created: number
+  updated: number
+  skipped: number
+  students: Student[]
+}
+
+// html 为带打印样式的网页，适合直接打印
+export type ExportFormat = 'xlsx' | 'csv' | 'html'
+
+export interface PurchaseRecordExportFilter {
+  startDate?: string
+  endDate?: string
+  source?: string
+  shippingStatus?: string
+}
+
+export interface ExportFileResult {
+  path: string
+  fileName: string
+  rowCount: number
+}
+
+export interface SavedFile {
+  path: string
+  fileName: string
+  directory: string
+}
+
+export interface TrashItem {
+  entityType: 'class' | 'student' | 'product'
+  id: string
+  name: string
+  classId?: string
+  className?: string
+  deletedAt: string
+}
+
+export interface PurgeTrashResult {
+  classes: number
+  students: number
+  products: number
+}
+
+export type ErrorCode =
+  | 'NOT_FOUND'
+  | 'INVALID_INPUT'
+  | 'INSUFFICIENT_STOCK'
+  | 'INSUFFICIENT_POINTS'
+  | 'CONSTRAINT_VIOLATION'
+  | 'WHEEL_NOT_CONFIGURED'
+  | 'CONFLICT'
+  | 'DATABASE_ERROR'
+  | 'IO_ERROR'
+  | 'INTERNAL_ERROR'
+
+// Every rejected command invoke carries this shape
+export interface AppError {
+  code: ErrorCode
+  message: string
+  details?: unknown
+}